@@ -1,50 +1,982 @@
 // Core file analyzer orchestration
 
-use crate::collector::ResultCollector;
-use crate::config::{AnalyzerConfig, TraversalStrategy};
+use crate::collector::{EntrySink, ResultCollector};
+use crate::config::{AnalyzerConfig, LimitBehavior, TraversalStrategy};
 use crate::error::AnalyzerError;
+use crate::estimate::{self, EntryCountEstimate};
+use crate::filesystem::{FileSystem, StdFileSystem};
 use crate::link_handler::LinkHandler;
+use crate::memory;
+use crate::observer::{ProgressCallback, ProgressObserver, ScanObserver};
+use crate::sample::{SplitMix64, random_seed};
 use crate::traversal::{
-    BreadthFirstTraversal, DepthFirstTraversal, TraversalStrategy as TraversalStrategyTrait,
+    BreadthFirstTraversal, DepthFirstTraversal, ParallelTraversal,
+    TraversalStrategy as TraversalStrategyTrait,
 };
 use crate::walker::DirectoryWalker;
 use rayon::ThreadPoolBuilder;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::SystemTime;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub size: u64,
     pub depth: usize,
     pub is_symlink: bool,
     pub target: Option<PathBuf>,
+    /// On-disk size, recorded when the size mode is `Allocated`
+    pub allocated_size: Option<u64>,
+    /// Owning user id, recorded when owner capture is enabled
+    pub uid: Option<u32>,
+    /// Owning group id, recorded when owner capture is enabled
+    pub gid: Option<u32>,
+    /// Size of a symlink's target, recorded when target sizes are kept
+    /// separately; `size` is then the link's own size
+    pub target_size: Option<u64>,
+    /// Canonical on-disk path, recorded when real paths are enabled and it
+    /// differs from `path` (the entry was reached through a symlink)
+    pub real_path: Option<PathBuf>,
+    /// Last modification time, recorded when file mtimes are enabled
+    pub modified: Option<SystemTime>,
+    /// MIME type detected from the file's leading bytes, recorded when
+    /// content sniffing is enabled and the type is recognized
+    pub content_type: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnalysisResult {
     pub total_size: u64,
+    /// Sum of on-disk sizes, recorded when the size mode is `Allocated`
+    #[serde(default)]
+    pub total_allocated_size: u64,
     pub file_count: usize,
     pub directory_count: usize,
     pub symlink_count: usize,
     pub entries: Vec<FileEntry>,
     pub warnings: Vec<String>,
+    /// Kind and path of each of `warnings`, in the same order
+    #[serde(default)]
+    pub warning_details: Vec<WarningDetail>,
     pub incomplete: bool,
+    /// File counts per power-of-two size bucket (see [`size_bucket`])
+    #[serde(default)]
+    pub size_histogram: Vec<usize>,
+    /// File counts per depth level, indexed by depth
+    #[serde(default)]
+    pub depth_histogram: Vec<usize>,
+    /// Directories where the scan crossed onto a different device
+    #[serde(default)]
+    pub mount_crossings: Vec<PathBuf>,
+    /// Directories where only the first entries were read (see
+    /// [`AnalyzerConfig::max_entries_per_dir`])
+    #[serde(default)]
+    pub truncated_dirs: Vec<PathBuf>,
+    /// Directory modification times, recorded when
+    /// [`AnalyzerConfig::record_dir_mtimes`] is set
+    #[serde(default)]
+    pub dir_mtimes: Vec<DirectoryMtime>,
+    /// Every directory entered, when [`AnalyzerConfig::record_directories`]
+    /// is set
+    #[serde(default)]
+    pub directories: Vec<PathBuf>,
+    /// Number of files below [`AnalyzerConfig::min_file_size`] per directory,
+    /// when [`AnalyzerConfig::count_small_files`] is set; they are left out of
+    /// every other figure
+    #[serde(default)]
+    pub small_file_counts: BTreeMap<PathBuf, usize>,
+    /// Context about the scan beyond the files found
+    #[serde(default)]
+    pub metadata: ScanMetadata,
+}
+
+/// Context recorded alongside a scan's results
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanMetadata {
+    /// Capacity in bytes of the volume holding the first root
+    pub disk_total: Option<u64>,
+    /// Free bytes on that volume available to the scanning user
+    pub disk_free: Option<u64>,
+    /// Seed of the run's randomized features; pass it back as
+    /// [`AnalyzerConfig::seed`] to reproduce them
+    pub seed: Option<u64>,
+    /// Peak resident memory of the scanning process in bytes, when tracked
+    pub peak_memory: Option<u64>,
+}
+
+/// What a warning is about
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// Access to a path was denied
+    PermissionDenied,
+    /// A path disappeared while the scan ran
+    NotFound,
+    /// Any other I/O error
+    Io,
+    /// A symlink leads back into a directory already scanned
+    SymlinkCycle,
+    /// A directory was reached again through another path to the same inode
+    DirectoryCycle,
+    /// Only the first entries of a directory were read
+    TruncatedDirectory,
+    /// A root was skipped for being missing or not a directory
+    InvalidRoot,
+    /// A line or member of a loaded manifest, NDJSON file or archive was
+    /// unreadable
+    MalformedInput,
+    #[default]
+    Other,
+}
+
+impl WarningKind {
+    /// Classify an I/O error
+    pub fn from_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => WarningKind::PermissionDenied,
+            io::ErrorKind::NotFound => WarningKind::NotFound,
+            _ => WarningKind::Io,
+        }
+    }
+
+    /// Classify an analyzer error, by its I/O error when it has one
+    pub fn from_error(error: &AnalyzerError) -> Self {
+        match error {
+            AnalyzerError::Io(e) => Self::from_io_error(e),
+            _ => WarningKind::Other,
+        }
+    }
+}
+
+/// Kind and path of a collected warning
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarningDetail {
+    pub kind: WarningKind,
+    /// Path the warning is about, when it is about one
+    pub path: Option<PathBuf>,
+}
+
+/// A directory's last modification time
+///
+/// A directory's mtime changes when entries are added, removed or renamed in
+/// it, so recent values point at areas of a disk with churn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryMtime {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+/// A file that was added, removed or resized between two scans
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryDiff {
+    pub path: PathBuf,
+    /// Size in the previous scan, `None` when the file was added
+    pub old_size: Option<u64>,
+    /// Size in the current scan, `None` when the file was removed
+    pub new_size: Option<u64>,
+}
+
+impl EntryDiff {
+    /// Size change in bytes (negative when the file shrank or was removed)
+    pub fn delta(&self) -> i128 {
+        self.new_size.unwrap_or(0) as i128 - self.old_size.unwrap_or(0) as i128
+    }
+}
+
+/// Total size and file count under a path prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixSize {
+    pub prefix: String,
+    pub total_size: u64,
+    pub file_count: usize,
+}
+
+/// A directory holding many files below the minimum size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmallFileHotspot {
+    pub path: PathBuf,
+    /// Files directly in the directory that were below the minimum size
+    pub small_file_count: usize,
+}
+
+/// Path and file name length limits for portability checks
+///
+/// Lengths are measured in bytes of the platform's path encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathLengthLimit {
+    /// Maximum length of a whole path
+    pub max_path: usize,
+    /// Maximum length of a single path component
+    pub max_component: usize,
+}
+
+impl Default for PathLengthLimit {
+    fn default() -> Self {
+        let max_path = if cfg!(windows) {
+            260
+        } else if cfg!(target_os = "macos") {
+            1024
+        } else {
+            4096
+        };
+        Self {
+            max_path,
+            max_component: 255,
+        }
+    }
+}
+
+impl PathLengthLimit {
+    /// Check if a path exceeds the whole-path or any component limit
+    pub fn is_exceeded_by(&self, path: &Path) -> bool {
+        path.as_os_str().len() > self.max_path
+            || path
+                .components()
+                .any(|c| c.as_os_str().len() > self.max_component)
+    }
+}
+
+impl AnalysisResult {
+    /// Build a result from a set of entries, recomputing totals and histograms
+    ///
+    /// Only directories that directly contain an entry are counted, and no
+    /// warnings or scan-time records (mount crossings, truncated directories,
+    /// directory mtimes) are carried over.
+    pub fn from_entries(entries: Vec<FileEntry>) -> Self {
+        let mut result = AnalysisResult {
+            total_size: 0,
+            total_allocated_size: 0,
+            file_count: entries.len(),
+            directory_count: 0,
+            symlink_count: 0,
+            entries: Vec::new(),
+            warnings: Vec::new(),
+            warning_details: Vec::new(),
+            incomplete: false,
+            size_histogram: Vec::new(),
+            depth_histogram: Vec::new(),
+            mount_crossings: Vec::new(),
+            truncated_dirs: Vec::new(),
+            dir_mtimes: Vec::new(),
+            directories: Vec::new(),
+            small_file_counts: BTreeMap::new(),
+            metadata: ScanMetadata::default(),
+        };
+
+        let mut directories = HashSet::new();
+        for entry in &entries {
+            result.total_size += entry.size;
+            result.total_allocated_size += entry.allocated_size.unwrap_or(0);
+            if entry.is_symlink {
+                result.symlink_count += 1;
+            }
+            bump(&mut result.size_histogram, size_bucket(entry.size));
+            bump(&mut result.depth_histogram, entry.depth);
+            if let Some(parent) = entry.path.parent() {
+                directories.insert(parent);
+            }
+        }
+        result.directory_count = directories.len();
+        result.entries = entries;
+        result
+    }
+
+    /// Record a warning along with its kind and path
+    pub fn add_warning(&mut self, kind: WarningKind, path: Option<PathBuf>, warning: String) {
+        self.warnings.push(warning);
+        self.warning_details.push(WarningDetail { kind, path });
+    }
+
+    /// Keep only the entries that pass the filters of `config`
+    ///
+    /// Applies the scan's filters to a result built from a manifest or
+    /// archive rather than scanned, see [`AnalyzerConfig::entry_matches`].
+    /// Totals and histograms are recomputed; warnings are kept.
+    pub fn filter_entries(&self, config: &AnalyzerConfig) -> AnalysisResult {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|e| config.entry_matches(e))
+            .cloned()
+            .collect();
+
+        let mut filtered = AnalysisResult::from_entries(entries);
+        filtered.warnings = self.warnings.clone();
+        filtered.warning_details = self.warning_details.clone();
+        filtered.incomplete = self.incomplete;
+        filtered
+    }
+
+    /// Pick `count` entries uniformly at random, in their original order
+    ///
+    /// The same seed always picks the same entries from the same result.
+    pub fn sample(&self, count: usize, seed: u64) -> Vec<&FileEntry> {
+        // Reservoir sampling over entry indices
+        let mut rng = SplitMix64::new(seed);
+        let mut reservoir: Vec<usize> = (0..count.min(self.entries.len())).collect();
+        for index in count..self.entries.len() {
+            let slot = rng.below(index as u64 + 1) as usize;
+            if slot < count {
+                reservoir[slot] = index;
+            }
+        }
+
+        reservoir.sort_unstable();
+        reservoir.into_iter().map(|i| &self.entries[i]).collect()
+    }
+
+    /// Drop entries smaller than `min_size`, recomputing totals and histograms
+    ///
+    /// Re-thresholds a collected or imported result without rescanning. The
+    /// directory count, warnings, metadata and scan-time records are kept, as
+    /// a scan with the same `min_file_size` would still visit every directory.
+    /// Dropped entries are added to
+    /// [`small_file_counts`](Self::small_file_counts) under their parent.
+    pub fn prune_below(&self, min_size: u64) -> AnalysisResult {
+        let mut small_file_counts = self.small_file_counts.clone();
+        let (entries, dropped): (Vec<_>, Vec<_>) = self
+            .entries
+            .iter()
+            .cloned()
+            .partition(|e| e.size >= min_size);
+        for entry in dropped {
+            if let Some(parent) = entry.path.parent() {
+                *small_file_counts.entry(parent.to_path_buf()).or_default() += 1;
+            }
+        }
+
+        let mut pruned = AnalysisResult::from_entries(entries);
+        pruned.small_file_counts = small_file_counts;
+        pruned.metadata = self.metadata.clone();
+        pruned.directory_count = self.directory_count;
+        pruned.warnings = self.warnings.clone();
+        pruned.warning_details = self.warning_details.clone();
+        pruned.incomplete = self.incomplete;
+        pruned.mount_crossings = self.mount_crossings.clone();
+        pruned.truncated_dirs = self.truncated_dirs.clone();
+        pruned.dir_mtimes = self.dir_mtimes.clone();
+        pruned.directories = self.directories.clone();
+        pruned
+    }
+
+    /// Split the entries into one result per top-level directory of a root
+    ///
+    /// Results are keyed by the directory name and hold paths relative to
+    /// that directory, with depths to match. Files directly in a root are
+    /// not part of any group; same-named directories under different roots
+    /// share a group. When [`directories`](Self::directories) were recorded,
+    /// every top-level directory gets a result, even an empty one, and
+    /// `directory_count` covers every directory in its subtree; otherwise
+    /// only directories directly holding a file are counted.
+    pub fn split_by_top_level_dir(&self, roots: &[PathBuf]) -> BTreeMap<String, AnalysisResult> {
+        let relative = |path: &Path| -> Option<PathBuf> {
+            roots
+                .iter()
+                .find_map(|root| path.strip_prefix(root).ok())
+                .map(Path::to_path_buf)
+        };
+        let top_name = |component: Component| component.as_os_str().to_string_lossy().into_owned();
+
+        let mut dir_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for dir in &self.directories {
+            if let Some(relative) = relative(dir)
+                && let Some(top) = relative.components().next()
+            {
+                *dir_counts.entry(top_name(top)).or_default() += 1;
+            }
+        }
+
+        let mut groups: BTreeMap<String, Vec<FileEntry>> = BTreeMap::new();
+        for name in dir_counts.keys() {
+            groups.insert(name.clone(), Vec::new());
+        }
+        for entry in &self.entries {
+            let Some(relative) = relative(&entry.path) else {
+                continue;
+            };
+            let mut components = relative.components();
+            if let Some(top) = components.next()
+                && !components.as_path().as_os_str().is_empty()
+            {
+                let mut entry = entry.clone();
+                entry.path = components.as_path().to_path_buf();
+                entry.depth = entry.depth.saturating_sub(1);
+                groups.entry(top_name(top)).or_default().push(entry);
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(name, entries)| {
+                let mut group = AnalysisResult::from_entries(entries);
+                if let Some(&count) = dir_counts.get(&name) {
+                    group.directory_count = count;
+                }
+                (name, group)
+            })
+            .collect()
+    }
+
+    /// Find entries whose path or any path component exceeds the limit
+    pub fn long_paths(&self, limit: PathLengthLimit) -> Vec<&FileEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| limit.is_exceeded_by(&entry.path))
+            .collect()
+    }
+
+    /// Compute total size and file count under each path prefix in one pass
+    ///
+    /// Prefixes are relative to `root` (see [`relative_to`]), e.g. `src` or
+    /// `./vendor/`, and match whole components, so `src` covers
+    /// `src/lib.rs` but not `srcs/lib.rs`. Prefixes may overlap: a file
+    /// counts toward every prefix it falls under. Results are returned in the
+    /// order the prefixes were given.
+    pub fn path_prefix_sizes<S: AsRef<str>>(&self, root: &Path, prefixes: &[S]) -> Vec<PrefixSize> {
+        let normalized: Vec<PathBuf> = prefixes
+            .iter()
+            .map(|p| {
+                Path::new(p.as_ref())
+                    .components()
+                    .filter(|c| !matches!(c, Component::CurDir))
+                    .collect()
+            })
+            .collect();
+        let mut sizes: Vec<PrefixSize> = prefixes
+            .iter()
+            .map(|p| PrefixSize {
+                prefix: p.as_ref().to_string(),
+                total_size: 0,
+                file_count: 0,
+            })
+            .collect();
+
+        for entry in &self.entries {
+            let relative = entry.relative_path(root);
+            for (prefix, size) in normalized.iter().zip(sizes.iter_mut()) {
+                if relative.starts_with(prefix) {
+                    size.total_size += entry.size;
+                    size.file_count += 1;
+                }
+            }
+        }
+
+        sizes
+    }
+
+    /// Sum file sizes per owning uid
+    ///
+    /// Entries without a recorded owner are not included.
+    pub fn size_by_owner(&self) -> BTreeMap<u32, u64> {
+        let mut sizes = BTreeMap::new();
+        for entry in &self.entries {
+            if let Some(uid) = entry.uid {
+                *sizes.entry(uid).or_insert(0) += entry.size;
+            }
+        }
+        sizes
+    }
+
+    /// Count, total, mean and median file size per extension
+    ///
+    /// Extensions are lowercased. Results are sorted by decreasing total
+    /// size, then by extension.
+    pub fn extension_stats(&self) -> Vec<ExtensionStats> {
+        let mut sizes: HashMap<String, Vec<u64>> = HashMap::new();
+        for entry in &self.entries {
+            let extension = entry
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            sizes.entry(extension).or_default().push(entry.size);
+        }
+
+        let mut stats: Vec<ExtensionStats> = sizes
+            .into_iter()
+            .map(|(extension, mut sizes)| {
+                sizes.sort_unstable();
+                let file_count = sizes.len();
+                let total_size: u64 = sizes.iter().sum();
+                let middle = file_count / 2;
+                let median_size = if file_count % 2 == 0 {
+                    (sizes[middle - 1] as f64 + sizes[middle] as f64) / 2.0
+                } else {
+                    sizes[middle] as f64
+                };
+                ExtensionStats {
+                    extension,
+                    file_count,
+                    total_size,
+                    mean_size: total_size as f64 / file_count as f64,
+                    median_size,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.extension.cmp(&b.extension))
+        });
+        stats
+    }
+
+    /// Compare against an earlier scan of the same roots
+    ///
+    /// Added and removed files are always reported; files present in both
+    /// only when their size changed by at least `min_delta` bytes (and by at
+    /// least one byte). Changes are sorted by decreasing magnitude.
+    pub fn diff(&self, previous: &AnalysisResult, min_delta: u64) -> Vec<EntryDiff> {
+        let old_sizes: HashMap<&Path, u64> = previous
+            .entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.size))
+            .collect();
+        let new_paths: HashSet<&Path> = self.entries.iter().map(|e| e.path.as_path()).collect();
+
+        let mut diffs: Vec<EntryDiff> = self
+            .entries
+            .iter()
+            .map(|e| EntryDiff {
+                path: e.path.clone(),
+                old_size: old_sizes.get(e.path.as_path()).copied(),
+                new_size: Some(e.size),
+            })
+            .filter(|d| match d.old_size {
+                Some(_) => d.delta() != 0 && d.delta().unsigned_abs() >= min_delta as u128,
+                None => true,
+            })
+            .collect();
+
+        diffs.extend(
+            previous
+                .entries
+                .iter()
+                .filter(|e| !new_paths.contains(e.path.as_path()))
+                .map(|e| EntryDiff {
+                    path: e.path.clone(),
+                    old_size: Some(e.size),
+                    new_size: None,
+                }),
+        );
+
+        diffs.sort_by(|a, b| {
+            b.delta()
+                .unsigned_abs()
+                .cmp(&a.delta().unsigned_abs())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        diffs
+    }
+
+    /// Find directories whose names differ only by case within the same parent
+    ///
+    /// Such directories merge or clash on case-insensitive filesystems.
+    /// Directories are taken from the paths of the entries, so directories
+    /// without any counted file are not considered. Each group of colliding
+    /// paths is sorted, and groups are sorted by their first path.
+    pub fn case_colliding_dirs(&self) -> Vec<Vec<PathBuf>> {
+        let mut directories: HashSet<&Path> = HashSet::new();
+        for entry in &self.entries {
+            for dir in entry.path.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() || !directories.insert(dir) {
+                    break;
+                }
+            }
+        }
+
+        let mut groups: HashMap<(&Path, String), Vec<PathBuf>> = HashMap::new();
+        for dir in directories {
+            if let (Some(parent), Some(name)) = (dir.parent(), dir.file_name()) {
+                let key = (parent, name.to_string_lossy().to_lowercase());
+                groups.entry(key).or_default().push(dir.to_path_buf());
+            }
+        }
+
+        let mut collisions: Vec<Vec<PathBuf>> = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort();
+                group
+            })
+            .collect();
+        collisions.sort();
+        collisions
+    }
+
+    /// Directories with at least `threshold_count` files below the minimum
+    /// size, most such files first
+    ///
+    /// Tiny files cost inodes and metadata out of proportion to their bytes,
+    /// so these point at bloat (e.g. `.git/objects` or caches) that totals
+    /// don't show. Only filled when the scan counted small files with a
+    /// nonzero minimum size.
+    pub fn small_file_hotspots(&self, threshold_count: usize) -> Vec<SmallFileHotspot> {
+        let mut hotspots: Vec<SmallFileHotspot> = self
+            .small_file_counts
+            .iter()
+            .filter(|(_, count)| **count >= threshold_count)
+            .map(|(path, count)| SmallFileHotspot {
+                path: path.clone(),
+                small_file_count: *count,
+            })
+            .collect();
+        // Counts come from a sorted map and the sort is stable, so ties stay
+        // in path order
+        hotspots.sort_by_key(|hotspot| std::cmp::Reverse(hotspot.small_file_count));
+        hotspots
+    }
+
+    /// Directories where the scan crossed onto a different device
+    pub fn mount_crossings(&self) -> Vec<PathBuf> {
+        self.mount_crossings.clone()
+    }
+
+    /// Directories modified at or after `since`, most recently modified first
+    pub fn recently_modified_dirs(&self, since: SystemTime) -> Vec<&DirectoryMtime> {
+        let mut dirs: Vec<&DirectoryMtime> = self
+            .dir_mtimes
+            .iter()
+            .filter(|d| d.modified >= since)
+            .collect();
+        dirs.sort_by(|a, b| {
+            b.modified
+                .cmp(&a.modified)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        dirs
+    }
+
+    /// Total size of symlink targets recorded with `target_size`
+    pub fn symlink_target_size(&self) -> u64 {
+        self.entries.iter().filter_map(|e| e.target_size).sum()
+    }
+
+    /// Total size of symlink targets that were not also scanned as regular
+    /// files, i.e. bytes reachable only via symlinks
+    ///
+    /// Targets are compared lexically (relative targets are resolved against
+    /// the link's directory), so a target reached through another symlinked
+    /// directory is treated as symlink-only.
+    pub fn symlink_only_size(&self) -> u64 {
+        let scanned: HashSet<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|e| !e.is_symlink)
+            .map(|e| lexical_absolute(&e.path))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut size = 0;
+        for entry in &self.entries {
+            let (Some(target), Some(target_size)) = (&entry.target, entry.target_size) else {
+                continue;
+            };
+            let target = match entry.path.parent() {
+                Some(parent) if target.is_relative() => lexical_absolute(&parent.join(target)),
+                _ => lexical_absolute(target),
+            };
+            if !scanned.contains(&target) && seen.insert(target) {
+                size += target_size;
+            }
+        }
+        size
+    }
+}
+
+/// Make a path absolute and resolve `.` and `..` components lexically
+pub(crate) fn lexical_absolute(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Get the size histogram bucket for a file size
+///
+/// Bucket 0 holds empty files, bucket `i` holds sizes in `[2^(i-1), 2^i)`.
+pub fn size_bucket(size: u64) -> usize {
+    (u64::BITS - size.leading_zeros()) as usize
+}
+
+/// Increment a histogram bucket, growing the histogram as needed
+fn bump(histogram: &mut Vec<usize>, bucket: usize) {
+    if histogram.len() <= bucket {
+        histogram.resize(bucket + 1, 0);
+    }
+    histogram[bucket] += 1;
+}
+
+/// Get the inclusive lower and exclusive upper bound of a size bucket
+pub fn size_bucket_range(bucket: usize) -> (u64, u128) {
+    match bucket {
+        0 => (0, 1),
+        _ => (1u64 << (bucket - 1), 1u128 << bucket),
+    }
+}
+
+/// File count and size figures of files sharing an extension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionStats {
+    /// Lowercase extension without the dot, empty for files without one
+    pub extension: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub mean_size: f64,
+    /// Middle size, or the average of the two middle sizes for an even count
+    pub median_size: f64,
+}
+
+impl FileEntry {
+    /// Get the path relative to `root`
+    ///
+    /// Returns the absolute path when the entry is not under `root`.
+    pub fn relative_path(&self, root: &Path) -> PathBuf {
+        relative_to(&self.path, root)
+    }
+
+    /// Get the path relative to `root` as a string with forward slashes
+    pub fn relative_path_string(&self, root: &Path) -> String {
+        normalize_separators(&self.relative_path(root))
+    }
+
+    /// Make the path and real path relative to `base`, see [`relative_to`]
+    pub fn relativize(&mut self, base: &Path) {
+        self.path = relative_to(&self.path, base);
+        if let Some(real_path) = &mut self.real_path {
+            *real_path = relative_to(real_path, base);
+        }
+    }
+}
+
+/// Get `path` relative to `root`, or as an absolute path when it is not under it
+///
+/// Relative paths are resolved against the current directory when they
+/// don't share a prefix with `root` as given.
+pub fn relative_to(path: &Path, root: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return relative.to_path_buf();
+    }
+
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let absolute_root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+    match absolute.strip_prefix(&absolute_root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => absolute,
+    }
+}
+
+/// Render a path with forward slashes regardless of platform
+pub fn normalize_separators(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
+
+impl AnalysisResult {
+    /// Make every recorded path relative to `base`, see [`relative_to`]
+    ///
+    /// Paths not under `base` become absolute. Warning messages keep the
+    /// paths they were written with.
+    pub fn relativize(&mut self, base: &Path) {
+        for entry in &mut self.entries {
+            entry.relativize(base);
+        }
+        let paths = self
+            .warning_details
+            .iter_mut()
+            .filter_map(|detail| detail.path.as_mut())
+            .chain(&mut self.mount_crossings)
+            .chain(&mut self.truncated_dirs)
+            .chain(self.dir_mtimes.iter_mut().map(|dir| &mut dir.path))
+            .chain(&mut self.directories);
+        for path in paths {
+            *path = relative_to(path, base);
+        }
+        self.small_file_counts = std::mem::take(&mut self.small_file_counts)
+            .into_iter()
+            .map(|(path, count)| (relative_to(&path, base), count))
+            .collect();
+    }
 }
 
 pub struct FileAnalyzer {
     config: AnalyzerConfig,
+    fs: Arc<dyn FileSystem>,
+    entry_sink: Option<EntrySink>,
+    retain_entries: bool,
+    observers: Vec<Arc<dyn ScanObserver>>,
+    progress: Option<ProgressCallback>,
+    cancel_flag: Option<Arc<AtomicBool>>,
 }
 
 impl FileAnalyzer {
     pub fn new(config: AnalyzerConfig) -> Self {
-        Self { config }
+        Self::with_file_system(config, Arc::new(StdFileSystem))
+    }
+
+    /// Create an analyzer that reads from a custom filesystem
+    pub fn with_file_system(mut config: AnalyzerConfig, fs: Arc<dyn FileSystem>) -> Self {
+        Self::exclude_output_path(&mut config, fs.as_ref());
+        Self {
+            config,
+            fs,
+            entry_sink: None,
+            retain_entries: true,
+            observers: Vec::new(),
+            progress: None,
+            cancel_flag: None,
+        }
+    }
+
+    /// Keep the output file and [`AnalyzerConfig::exclude_output_dir`] out of
+    /// the scan
+    ///
+    /// The output may not exist yet, so its parent directory is canonicalized
+    /// and compared with each canonicalized root; with
+    /// [`AnalyzerConfig::no_symlinks`] both are only normalized lexically. A
+    /// match is excluded by the path the traversal will reach it under. A
+    /// root itself is never excluded.
+    fn exclude_output_path(config: &mut AnalyzerConfig, fs: &dyn FileSystem) {
+        let resolve = |path: &Path| {
+            let path = if path.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                path
+            };
+            if config.no_symlinks {
+                Some(lexical_absolute(path))
+            } else {
+                fs.canonicalize(path).ok()
+            }
+        };
+        // Where a resolved location lies under each root, relative to it
+        let under_roots = |location: &Path| -> Vec<(PathBuf, PathBuf)> {
+            config
+                .roots()
+                .filter_map(|root| {
+                    let relative = location.strip_prefix(resolve(root)?).ok()?;
+                    Some((root.clone(), relative.to_path_buf()))
+                })
+                .collect()
+        };
+
+        let mut excluded = Vec::new();
+        if let Some(output) = &config.output_path
+            && let (Some(parent), Some(name)) = (output.parent(), output.file_name())
+            && let Some(parent) = resolve(parent)
+        {
+            for (root, relative) in under_roots(&parent) {
+                excluded.push(root.join(relative).join(name));
+            }
+        }
+        if let Some(dir) = &config.exclude_output_dir
+            && let Some(dir) = resolve(dir)
+        {
+            for (root, relative) in under_roots(&dir) {
+                if !relative.as_os_str().is_empty() {
+                    excluded.push(root.join(relative));
+                }
+            }
+        }
+        config.excluded_paths.extend(excluded);
+    }
+
+    /// The configuration the analyzer runs with, including the output paths
+    /// it excludes
+    pub fn config(&self) -> &AnalyzerConfig {
+        &self.config
+    }
+
+    /// Stream every collected entry to a callback while the scan runs
+    pub fn set_entry_sink(&mut self, sink: EntrySink) {
+        self.entry_sink = Some(sink);
+    }
+
+    /// Set whether entries are kept in the result, e.g. `false` when they are
+    /// only streamed to a sink; totals and histograms are collected either way
+    ///
+    /// Entries are never kept with [`AnalyzerConfig::stats_only`].
+    pub fn set_retain_entries(&mut self, retain: bool) {
+        self.retain_entries = retain;
+    }
+
+    /// Notify an observer of directories, files and warnings while the scan runs
+    pub fn add_observer(&mut self, observer: Arc<dyn ScanObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Report progress after each counted file
+    ///
+    /// When the root volume's capacity is known, progress includes the share
+    /// of its used space scanned so far as a completion estimate.
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress = Some(callback);
+    }
+
+    /// Stop the scan with [`AnalyzerError::Cancelled`] once the flag is raised
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel_flag = Some(flag);
+    }
+
+    /// Run the analysis on tokio's blocking pool without blocking the executor
+    ///
+    /// Cancelling the token stops the traversal, which then returns
+    /// [`AnalyzerError::Cancelled`].
+    #[cfg(feature = "tokio")]
+    pub async fn analyze_async(
+        mut self,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<AnalysisResult, AnalyzerError> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.set_cancel_flag(flag.clone());
+
+        // Raise the flag the traversal polls when the token is cancelled
+        let watcher = tokio::spawn(async move {
+            cancel.cancelled().await;
+            flag.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+
+        let result = tokio::task::spawn_blocking(move || self.analyze()).await;
+        watcher.abort();
+        result.map_err(|e| AnalyzerError::ThreadPool(e.to_string()))?
+    }
+
+    /// Quickly estimate how many files a scan will find
+    ///
+    /// Lists a bounded sample of directories instead of the whole tree, so
+    /// it returns fast enough to size a progress bar before calling
+    /// [`analyze`](Self::analyze). Sampling is seeded with
+    /// [`AnalyzerConfig::seed`], or a fresh seed when it is unset.
+    pub fn estimate_entry_count(&self) -> EntryCountEstimate {
+        let seed = self.config.seed.unwrap_or_else(random_seed);
+        estimate::estimate_entry_count(&self.config, self.fs.clone(), seed)
+    }
+
+    /// Check the preconditions of [`analyze`](Self::analyze) without scanning
+    ///
+    /// Validates the configuration and its roots against the analyzer's
+    /// filesystem. Ignore patterns are compiled when they are set, so an
+    /// invalid glob is reported by `AnalyzerConfig::set_ignore_patterns`.
+    pub fn validate_only(&self) -> Result<(), AnalyzerError> {
+        self.config.validate_with(self.fs.as_ref())
     }
 
     pub fn analyze(&self) -> Result<AnalysisResult, AnalyzerError> {
         // Validate configuration
-        self.config.validate()?;
+        self.validate_only()?;
 
         // #[cfg(feature = "progress")]
         // {
@@ -54,12 +986,62 @@ impl FileAnalyzer {
         // #[cfg(not(feature = "progress"))]
         // {
         // Choose between single-threaded and multi-threaded
-        if self.config.thread_count == 1 {
-            self.analyze_single_threaded()
+        // The parallel strategy always runs in a sized pool so it honors thread_count
+        let mut result = if self.config.thread_count == 1
+            && self.config.traversal_strategy != TraversalStrategy::Parallel
+        {
+            self.analyze_single_threaded()?
         } else {
-            self.analyze_multi_threaded()
-        }
+            self.analyze_multi_threaded()?
+        };
         // }
+
+        result.metadata.seed = self.config.seed;
+        if let Some(space) = self.fs.disk_space(&self.config.root_path) {
+            result.metadata.disk_total = Some(space.total);
+            result.metadata.disk_free = Some(space.free);
+        }
+        if self.config.track_peak_memory {
+            result.metadata.peak_memory = memory::peak_rss();
+        }
+
+        self.check_limits(result)
+    }
+
+    /// Turn a truncated result into an error when limits are configured to fail
+    fn check_limits(&self, result: AnalysisResult) -> Result<AnalysisResult, AnalyzerError> {
+        if self.config.limit_behavior == LimitBehavior::Truncate {
+            return Ok(result);
+        }
+
+        if result.incomplete
+            && let Some(max_size) = self.config.max_total_size
+            && result.total_size >= max_size
+        {
+            return Err(AnalyzerError::LimitReached(format!(
+                "stopped after {} bytes (--max-total-size)",
+                result.total_size
+            )));
+        }
+        if result.incomplete
+            && let Some(max_files) = self.config.max_files
+        {
+            return Err(AnalyzerError::LimitReached(format!(
+                "stopped after {} files (--max-files)",
+                max_files
+            )));
+        }
+        if let Some(dir) = result.truncated_dirs.first()
+            && let Some(max_entries) = self.config.max_entries_per_dir
+        {
+            return Err(AnalyzerError::LimitReached(format!(
+                "more than {} entries in {} (--max-entries-per-dir)",
+                max_entries,
+                dir.display()
+            )));
+        }
+
+        Ok(result)
     }
 
     // #[cfg(feature = "progress")]
@@ -85,24 +1067,47 @@ impl FileAnalyzer {
     // }
 
     fn analyze_single_threaded(&self) -> Result<AnalysisResult, AnalyzerError> {
-        let link_handler = Arc::new(LinkHandler::new());
-        let walker = DirectoryWalker::new(link_handler.clone());
-        let collector = ResultCollector::new();
+        let link_handler = Arc::new(LinkHandler::new(self.fs.clone()));
+        let mut walker = DirectoryWalker::new(link_handler.clone(), self.fs.clone());
+        walker.set_max_entries_per_dir(self.config.max_entries_per_dir);
+        let mut collector = ResultCollector::new();
+        collector.set_retain_entries(self.retain_entries && !self.config.stats_only);
+        collector.set_record_directories(self.config.record_directories);
+        collector.set_entry_sink(self.entry_sink.clone());
+        let mut observers = self.observers.clone();
+        if let Some(ref callback) = self.progress {
+            let used_space = self
+                .fs
+                .disk_space(&self.config.root_path)
+                .map(|space| space.total.saturating_sub(space.free));
+            observers.push(Arc::new(ProgressObserver::new(
+                callback.clone(),
+                used_space,
+            )));
+        }
+        collector.set_observers(observers);
+        collector.set_cancel_flag(self.cancel_flag.clone());
 
         // Select traversal strategy
         let strategy: Box<dyn TraversalStrategyTrait> = match self.config.traversal_strategy {
             TraversalStrategy::DepthFirst => Box::new(DepthFirstTraversal::new()),
             TraversalStrategy::BreadthFirst => Box::new(BreadthFirstTraversal::new()),
+            TraversalStrategy::Parallel => Box::new(ParallelTraversal::new()),
         };
 
-        // Perform traversal
-        strategy.traverse(
-            &self.config.root_path,
-            &self.config,
-            &walker,
-            &link_handler,
-            &collector,
-        )?;
+        // Perform traversal of each root, skipping invalid ones if allowed
+        for root in self.config.roots() {
+            if let Err(e) = AnalyzerConfig::validate_root(root, self.fs.as_ref()) {
+                collector.add_warning_at(
+                    WarningKind::InvalidRoot,
+                    Some(root),
+                    format!("Skipping root {}: {}", root.display(), e),
+                );
+                continue;
+            }
+
+            strategy.traverse(root, &self.config, &walker, &link_handler, &collector)?;
+        }
 
         Ok(collector.finalize())
     }
@@ -114,10 +1119,678 @@ impl FileAnalyzer {
             .build()
             .map_err(|e| AnalyzerError::ThreadPool(e.to_string()))?;
 
-        // For now, use single-threaded approach within the pool
-        // Full multi-threaded implementation would require more complex coordination
+        // Sequential strategies run as-is within the pool, while the parallel
+        // strategy distributes subdirectories across the pool's threads
         let result = pool.install(|| self.analyze_single_threaded())?;
 
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFileSystem;
+    use crate::config::{RootErrorPolicy, SizeMode};
+    use crate::test_support::{entry, result_of, scan};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn tree() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/empty.txt", 0)
+            .add_file("/data/a.txt", 1)
+            .add_file("/data/src/b.rs", 100)
+            .add_file("/data/src/lib/c.rs", 1000);
+        fs
+    }
+
+    #[test]
+    fn stats_only_keeps_totals_and_histograms() {
+        let full = scan(AnalyzerConfig::new(PathBuf::from("/data")), tree());
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.stats_only = true;
+        let stats = scan(config, tree());
+
+        assert!(stats.entries.is_empty());
+        assert_eq!(stats.file_count, 4);
+        assert_eq!(stats.total_size, 1101);
+        assert_eq!(stats.size_histogram, full.size_histogram);
+        assert_eq!(stats.depth_histogram, full.depth_histogram);
+        // 0, 1, 100 and 1000 bytes fall in buckets 0, 1, 7 and 10
+        assert_eq!(stats.size_histogram, [1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn relativize_rewrites_paths_under_base() {
+        let mut result = result_of(vec![entry("/data/src/b.rs", 20), entry("/other/c.rs", 30)]);
+        result.add_warning(
+            WarningKind::Io,
+            Some(PathBuf::from("/data/src")),
+            "Cannot read directory /data/src".to_string(),
+        );
+        result
+            .small_file_counts
+            .insert(PathBuf::from("/data/src"), 3);
+        result.relativize(Path::new("/data"));
+
+        assert_eq!(result.entries[0].path, Path::new("src/b.rs"));
+        // Paths outside the base stay absolute
+        assert_eq!(result.entries[1].path, Path::new("/other/c.rs"));
+        assert_eq!(
+            result.warning_details[0].path.as_deref(),
+            Some(Path::new("src"))
+        );
+        assert_eq!(result.warnings[0], "Cannot read directory /data/src");
+        assert_eq!(result.small_file_counts[Path::new("src")], 3);
+    }
+
+    #[test]
+    fn prefix_sizes_count_overlapping_prefixes() {
+        let result = result_of(vec![
+            entry("/data/src/lib.rs", 10),
+            entry("/data/src/bin/main.rs", 20),
+            entry("/data/srcs/x.rs", 40),
+            entry("/data/README.md", 80),
+        ]);
+        let sizes = result.path_prefix_sizes(Path::new("/data"), &["src", "./src/bin/", "docs"]);
+
+        let totals: Vec<(u64, usize)> =
+            sizes.iter().map(|s| (s.total_size, s.file_count)).collect();
+        assert_eq!(totals, [(30, 2), (20, 1), (0, 0)]);
+        assert_eq!(sizes[1].prefix, "./src/bin/");
+    }
+
+    #[test]
+    fn long_paths_checks_whole_path_and_components() {
+        // 23 bytes in all, but a 17 byte file name
+        let long_name = format!("/data/{}.txt", "n".repeat(13));
+        let result = result_of(vec![
+            entry("/data/short.txt", 1),
+            entry(&long_name, 1),
+            entry("/data/a/b/c/d/e/f/g/h.txt", 1),
+        ]);
+        let limit = PathLengthLimit {
+            max_path: 24,
+            max_component: 16,
+        };
+
+        let long: Vec<&Path> = result
+            .long_paths(limit)
+            .into_iter()
+            .map(|e| e.path.as_path())
+            .collect();
+        assert_eq!(
+            long,
+            [
+                Path::new(&long_name),
+                Path::new("/data/a/b/c/d/e/f/g/h.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_roots_abort_or_are_skipped() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.additional_roots = vec![PathBuf::from("/missing")];
+        let analyzer = FileAnalyzer::with_file_system(config.clone(), Arc::new(tree()));
+        assert!(matches!(
+            analyzer.analyze(),
+            Err(AnalyzerError::InvalidConfig(_))
+        ));
+
+        config.root_error_policy = RootErrorPolicy::Continue;
+        let result = scan(config.clone(), tree());
+        assert_eq!(result.file_count, 4);
+        assert_eq!(
+            result.warnings,
+            ["Skipping root /missing: Invalid configuration: Root path does not exist: /missing"]
+        );
+        assert_eq!(result.warning_details[0].kind, WarningKind::InvalidRoot);
+        assert_eq!(
+            result.warning_details[0].path.as_deref(),
+            Some(Path::new("/missing"))
+        );
+
+        // Continuing still needs one valid root
+        config.root_path = PathBuf::from("/gone");
+        let analyzer = FileAnalyzer::with_file_system(config, Arc::new(tree()));
+        assert!(analyzer.analyze().is_err());
+    }
+
+    #[test]
+    fn relative_path_strips_the_root() {
+        let entry = entry("/data/src/lib.rs", 1);
+        assert_eq!(
+            entry.relative_path(Path::new("/data")),
+            Path::new("src/lib.rs")
+        );
+        assert_eq!(
+            entry.relative_path(Path::new("/data/")),
+            Path::new("src/lib.rs")
+        );
+        assert_eq!(entry.relative_path_string(Path::new("/data")), "src/lib.rs");
+        // Outside the root the absolute path is kept
+        assert_eq!(
+            entry.relative_path(Path::new("/other")),
+            Path::new("/data/src/lib.rs")
+        );
+    }
+
+    #[test]
+    fn relative_path_resolves_relative_roots() {
+        let cwd = std::env::current_dir().unwrap();
+        let entry = entry(cwd.join("src/lib.rs").to_str().unwrap(), 1);
+        assert_eq!(entry.relative_path(Path::new(".")), Path::new("src/lib.rs"));
+    }
+
+    #[test]
+    fn validate_only_checks_without_scanning() {
+        let mut fs = tree();
+        fs.add_file("/file.txt", 1).deny("/data/src");
+        let validate = |config: AnalyzerConfig| {
+            FileAnalyzer::with_file_system(config, Arc::new(fs.clone())).validate_only()
+        };
+
+        // An unreadable subdirectory is only found by a scan
+        assert!(validate(AnalyzerConfig::new(PathBuf::from("/data"))).is_ok());
+        assert!(validate(AnalyzerConfig::new(PathBuf::from("/missing"))).is_err());
+        assert!(validate(AnalyzerConfig::new(PathBuf::from("/file.txt"))).is_err());
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.max_depth = Some(0);
+        assert!(matches!(
+            validate(config),
+            Err(AnalyzerError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn total_allocated_size_sums_entries() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.size_mode = SizeMode::Allocated;
+        let result = scan(config, tree());
+
+        let sum: u64 = result.entries.iter().filter_map(|e| e.allocated_size).sum();
+        assert_eq!(result.total_allocated_size, sum);
+        // Without block information the logical sizes stand in
+        assert_eq!(result.total_allocated_size, result.total_size);
+
+        let mut rebuilt = result.entries.clone();
+        rebuilt[0].allocated_size = Some(4096);
+        let rebuilt = result_of(rebuilt);
+        assert_eq!(
+            rebuilt.total_allocated_size,
+            sum - result.entries[0].size + 4096
+        );
+
+        let logical = scan(AnalyzerConfig::new(PathBuf::from("/data")), tree());
+        assert_eq!(logical.total_allocated_size, 0);
+    }
+
+    #[test]
+    fn limits_truncate_or_fail() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.max_files = Some(2);
+        let truncated = scan(config.clone(), tree());
+        assert!(truncated.incomplete);
+        assert_eq!(truncated.file_count, 2);
+
+        config.limit_behavior = LimitBehavior::Error;
+        let analyzer = FileAnalyzer::with_file_system(config.clone(), Arc::new(tree()));
+        assert!(matches!(
+            analyzer.analyze(),
+            Err(AnalyzerError::LimitReached(_))
+        ));
+
+        // A scan within the limits succeeds either way
+        config.max_files = Some(10);
+        assert!(!scan(config, tree()).incomplete);
+    }
+
+    #[test]
+    fn recently_modified_dirs_newest_first() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut fs = tree();
+        fs.set_modified("/data", at(100))
+            .set_modified("/data/src", at(300))
+            .set_modified("/data/src/lib", at(200));
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.record_dir_mtimes = true;
+        let result = scan(config, fs);
+
+        let recent: Vec<&Path> = result
+            .recently_modified_dirs(at(200))
+            .into_iter()
+            .map(|d| d.path.as_path())
+            .collect();
+        assert_eq!(recent, [Path::new("/data/src"), Path::new("/data/src/lib")]);
+
+        // Not recorded unless asked for
+        assert!(
+            scan(AnalyzerConfig::new(PathBuf::from("/data")), tree())
+                .dir_mtimes
+                .is_empty()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recently_modified_dirs_on_disk() {
+        let root = std::env::temp_dir().join(format!("wiztree-mtimes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("old")).unwrap();
+        std::fs::create_dir_all(root.join("new")).unwrap();
+        let now = SystemTime::now();
+        let week_ago = now - Duration::from_secs(7 * 24 * 60 * 60);
+        for (dir, modified) in [("old", week_ago), (".", week_ago), ("new", now)] {
+            std::fs::File::open(root.join(dir))
+                .and_then(|dir| dir.set_modified(modified))
+                .unwrap();
+        }
+
+        let mut config = AnalyzerConfig::new(root.clone());
+        config.record_dir_mtimes = true;
+        let result = FileAnalyzer::new(config).analyze();
+        std::fs::remove_dir_all(&root).unwrap();
+        let result = result.unwrap();
+
+        assert_eq!(result.dir_mtimes.len(), 3);
+        let recent: Vec<&Path> = result
+            .recently_modified_dirs(now - Duration::from_secs(60))
+            .into_iter()
+            .map(|d| d.path.as_path())
+            .collect();
+        assert_eq!(recent, [root.join("new").as_path()]);
+    }
+
+    #[test]
+    fn older_scans_load_with_defaults() {
+        // A result as written before any optional fields were added
+        let json = r#"{
+            "total_size": 10,
+            "file_count": 1,
+            "directory_count": 1,
+            "symlink_count": 0,
+            "entries": [
+                {"path": "/data/a.txt", "size": 10, "depth": 1, "is_symlink": false, "target": null}
+            ],
+            "warnings": [],
+            "incomplete": false
+        }"#;
+        let previous: AnalysisResult = serde_json::from_str(json).unwrap();
+
+        assert_eq!(previous.total_allocated_size, 0);
+        assert_eq!(previous.metadata, ScanMetadata::default());
+        assert_eq!(previous.entries[0].allocated_size, None);
+        let current = result_of(vec![entry("/data/a.txt", 25)]);
+        assert_eq!(current.diff(&previous, 0)[0].delta(), 15);
+    }
+
+    #[test]
+    fn diff_hides_changes_below_the_threshold() {
+        let previous = result_of(vec![
+            entry("/data/grown", 100),
+            entry("/data/nudged", 100),
+            entry("/data/same", 100),
+            entry("/data/removed", 1),
+        ]);
+        let current = result_of(vec![
+            entry("/data/grown", 1100),
+            entry("/data/nudged", 90),
+            entry("/data/same", 100),
+            entry("/data/added", 1),
+        ]);
+
+        let changes = |min_delta| -> Vec<(String, i128)> {
+            current
+                .diff(&previous, min_delta)
+                .iter()
+                .map(|d| (d.path.display().to_string(), d.delta()))
+                .collect()
+        };
+        // Added and removed files show whatever their size
+        assert_eq!(
+            changes(50),
+            [
+                ("/data/grown".to_string(), 1000),
+                ("/data/added".to_string(), 1),
+                ("/data/removed".to_string(), -1),
+            ]
+        );
+        assert_eq!(changes(10).len(), 4);
+        assert_eq!(changes(0).len(), 4);
+    }
+
+    #[test]
+    fn split_by_top_level_dir_groups_files() {
+        let mut fs = tree();
+        for i in 0..3 {
+            fs.add_file(format!("/data/docs/{}.md", i), 1);
+        }
+        fs.add_dir("/data/empty/nested");
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.record_directories = true;
+        let result = scan(config, fs);
+
+        let groups = result.split_by_top_level_dir(&[PathBuf::from("/data")]);
+        let names: Vec<&str> = groups.keys().map(String::as_str).collect();
+        assert_eq!(names, ["docs", "empty", "src"]);
+
+        assert_eq!(groups["docs"].file_count, 3);
+        assert_eq!(groups["src"].file_count, 2);
+        assert_eq!(groups["src"].total_size, 1100);
+        assert_eq!(groups["src"].directory_count, 2);
+        assert_eq!(groups["empty"].file_count, 0);
+        assert_eq!(groups["empty"].directory_count, 2);
+
+        let paths: Vec<&Path> = groups["src"]
+            .entries
+            .iter()
+            .map(|e| e.path.as_path())
+            .collect();
+        assert!(paths.contains(&Path::new("lib/c.rs")));
+        assert!(paths.contains(&Path::new("b.rs")));
+    }
+
+    #[test]
+    fn raised_cancel_flag_stops_the_scan() {
+        let mut analyzer = FileAnalyzer::with_file_system(
+            AnalyzerConfig::new(PathBuf::from("/data")),
+            Arc::new(tree()),
+        );
+        analyzer.set_cancel_flag(Arc::new(AtomicBool::new(true)));
+        assert!(matches!(analyzer.analyze(), Err(AnalyzerError::Cancelled)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn analyze_async_runs_and_cancels() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let analyzer = || {
+            FileAnalyzer::with_file_system(
+                AnalyzerConfig::new(PathBuf::from("/data")),
+                Arc::new(tree()),
+            )
+        };
+
+        let token = tokio_util::sync::CancellationToken::new();
+        let result = runtime.block_on(analyzer().analyze_async(token)).unwrap();
+        assert_eq!(result.file_count, 4);
+
+        // Cancel once the first file is found, giving the watcher time to
+        // raise the flag before the traversal moves on
+        let token = tokio_util::sync::CancellationToken::new();
+        let mut analyzer = analyzer();
+        let cancel = token.clone();
+        analyzer.set_entry_sink(Arc::new(move |_| {
+            if !cancel.is_cancelled() {
+                cancel.cancel();
+                std::thread::sleep(Duration::from_millis(200));
+            }
+        }));
+        let cancelled = runtime.block_on(analyzer.analyze_async(token));
+        assert!(matches!(cancelled, Err(AnalyzerError::Cancelled)));
+    }
+
+    #[test]
+    fn case_colliding_dirs_are_grouped_per_parent() {
+        let result = result_of(vec![
+            entry("/data/Docs/a.md", 1),
+            entry("/data/docs/b.md", 1),
+            entry("/data/DOCS/sub/c.md", 1),
+            entry("/data/x/Lib/d.rs", 1),
+            entry("/data/y/lib/e.rs", 1),
+            entry("/data/README", 1),
+            entry("/data/readme/f", 1),
+        ]);
+
+        assert_eq!(
+            result.case_colliding_dirs(),
+            [vec![
+                PathBuf::from("/data/DOCS"),
+                PathBuf::from("/data/Docs"),
+                PathBuf::from("/data/docs"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn size_by_owner_sums_per_uid() {
+        let mut fs = tree();
+        fs.set_owner("/data/a.txt", 1000, 1000)
+            .set_owner("/data/src/b.rs", 1000, 100)
+            .set_owner("/data/src/lib/c.rs", 0, 0);
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.capture_owner = true;
+        let result = scan(config, fs);
+
+        // empty.txt has no recorded owner and is left out
+        assert_eq!(
+            result.size_by_owner().into_iter().collect::<Vec<_>>(),
+            [(0, 1000), (1000, 101)]
+        );
+    }
+
+    #[test]
+    fn extension_stats_mean_and_median() {
+        let result = result_of(vec![
+            entry("/data/a.rs", 1),
+            entry("/data/b.RS", 2),
+            entry("/data/c.rs", 9),
+            entry("/data/d.md", 10),
+            entry("/data/e.md", 20),
+            entry("/data/Makefile", 3),
+        ]);
+        let stats = result.extension_stats();
+
+        let summary: Vec<(&str, usize, u64, f64, f64)> = stats
+            .iter()
+            .map(|s| {
+                (
+                    s.extension.as_str(),
+                    s.file_count,
+                    s.total_size,
+                    s.mean_size,
+                    s.median_size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("md", 2, 30, 15.0, 15.0),
+                ("rs", 3, 12, 4.0, 2.0),
+                ("", 1, 3, 3.0, 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn output_file_is_excluded_from_the_scan() {
+        let mut fs = tree();
+        fs.add_file("/data/src/out.json", 5000)
+            .add_file("/data/out.json", 7)
+            .add_symlink("/data/via", "src");
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.output_path = Some(PathBuf::from("/data/via/out.json"));
+        let result = scan(config, fs);
+
+        // Reached under /data/src, not the path it was given as
+        assert!(
+            !result
+                .entries
+                .iter()
+                .any(|e| e.path == Path::new("/data/src/out.json"))
+        );
+        assert!(
+            result
+                .entries
+                .iter()
+                .any(|e| e.path == Path::new("/data/out.json"))
+        );
+        assert_eq!(result.total_size, 1108);
+    }
+
+    #[test]
+    fn scan_warnings_carry_kind_and_path() {
+        let mut fs = tree();
+        fs.deny("/data/src");
+        let result = scan(AnalyzerConfig::new(PathBuf::from("/data")), fs);
+
+        assert_eq!(result.warnings.len(), result.warning_details.len());
+        assert!(result.warning_details.iter().any(|d| {
+            d.kind == WarningKind::PermissionDenied
+                && d.path.as_deref() == Some(Path::new("/data/src"))
+        }));
+    }
+
+    #[test]
+    fn prune_below_matches_a_fresh_scan() {
+        let linked = || {
+            let mut fs = tree();
+            fs.add_symlink("/data/c-link", "/data/src/lib/c.rs");
+            fs
+        };
+        let config = |min_file_size| {
+            let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+            config.min_file_size = min_file_size;
+            config.count_small_files = true;
+            config.seed = Some(7);
+            config
+        };
+        let pruned = scan(config(0), linked()).prune_below(50);
+        let fresh = scan(config(50), linked());
+
+        assert_eq!(pruned.total_size, fresh.total_size);
+        assert_eq!(pruned.file_count, fresh.file_count);
+        assert_eq!(pruned.symlink_count, fresh.symlink_count);
+        assert_eq!(pruned.directory_count, fresh.directory_count);
+        assert_eq!(pruned.size_histogram, fresh.size_histogram);
+        assert_eq!(pruned.metadata, fresh.metadata);
+        assert!(!pruned.small_file_counts.is_empty());
+        assert_eq!(pruned.small_file_counts, fresh.small_file_counts);
+    }
+
+    #[test]
+    fn seed_is_recorded_only_when_set() {
+        let result = scan(AnalyzerConfig::new(PathBuf::from("/data")), tree());
+        assert_eq!(result.metadata.seed, None);
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.seed = Some(7);
+        assert_eq!(scan(config, tree()).metadata.seed, Some(7));
+    }
+
+    #[test]
+    fn output_dir_is_pruned_from_the_scan() {
+        let reports = || {
+            let mut fs = tree();
+            fs.add_file("/data/reports/run1.json", 5000)
+                .add_file("/data/reports/old/run0.json", 4000)
+                .add_file("/data/reports.txt", 3);
+            fs
+        };
+        let unpruned = scan(AnalyzerConfig::new(PathBuf::from("/data")), reports());
+        assert_eq!(unpruned.total_size, 10104);
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.exclude_output_dir = Some(PathBuf::from("/data/./reports"));
+        let result = scan(config, reports());
+
+        assert!(
+            !result
+                .entries
+                .iter()
+                .any(|e| e.path.starts_with("/data/reports"))
+        );
+        assert_eq!(result.total_size, 1104);
+        assert_eq!(result.directory_count, unpruned.directory_count - 2);
+    }
+
+    #[test]
+    fn only_files_newer_than_the_reference_are_kept() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut fs = tree();
+        fs.add_file("/data/stamp", 0)
+            .set_modified("/data/stamp", at(2000))
+            .set_modified("/data/a.txt", at(1000))
+            .set_modified("/data/src/b.rs", at(2000))
+            .set_modified("/data/src/lib/c.rs", at(3000));
+        // The cutoff is the reference file's own mtime, as with find -newer
+        let stamp = Path::new("/data/stamp");
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.set_newer_than(stamp, &fs).unwrap();
+        let newer = scan(config, fs.clone());
+        let paths: Vec<&Path> = newer.entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/data/src/lib/c.rs")]);
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.set_older_than(stamp, &fs).unwrap();
+        let older = scan(config, fs.clone());
+        let paths: Vec<&Path> = older.entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/data/a.txt")]);
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        assert!(matches!(
+            config.set_newer_than(Path::new("/data/missing"), &fs),
+            Err(AnalyzerError::PathError(_))
+        ));
+        assert_eq!(config.modified_after, None);
+    }
+
+    #[test]
+    fn directories_of_tiny_files_are_hotspots() {
+        let mut fs = tree();
+        for i in 0..50 {
+            fs.add_file(format!("/data/.git/objects/{:02}", i), 10);
+        }
+        fs.add_file("/data/cache/x", 1).add_file("/data/cache/y", 2);
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.min_file_size = 64;
+        config.count_small_files = true;
+        let result = scan(config, fs);
+
+        let hotspots: Vec<(PathBuf, usize)> = result
+            .small_file_hotspots(2)
+            .into_iter()
+            .map(|h| (h.path, h.small_file_count))
+            .collect();
+        assert_eq!(
+            hotspots,
+            [
+                (PathBuf::from("/data/.git/objects"), 50),
+                (PathBuf::from("/data"), 2),
+                (PathBuf::from("/data/cache"), 2),
+            ]
+        );
+        assert_eq!(result.small_file_hotspots(10).len(), 1);
+        // Small files stay out of the totals
+        assert_eq!(result.file_count, 2);
+        assert_eq!(result.total_size, 1100);
+    }
+
+    #[test]
+    fn small_files_are_counted_after_filters_and_link_dedup() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/cache/tiny", 10)
+            .set_owner("/data/cache/tiny", 1000, 1000)
+            .add_file("/data/cache/theirs", 10)
+            .set_owner("/data/cache/theirs", 0, 0)
+            .add_file("/data/big", 1000)
+            .set_owner("/data/big", 1000, 1000);
+        for i in 0..3 {
+            fs.add_hard_link(format!("/data/cache/link-{}", i), "/data/cache/tiny");
+        }
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.min_file_size = 64;
+        config.count_small_files = true;
+        config.owner = Some(1000);
+        let result = scan(config, fs);
+
+        // Four names for one inode count once, another owner's file not at all
+        assert_eq!(result.small_file_counts[Path::new("/data/cache")], 1);
+        assert_eq!(result.file_count, 1);
+    }
+}
@@ -0,0 +1,167 @@
+// Results built from the member list of a tar archive
+
+use crate::analyzer::{AnalysisResult, FileEntry, WarningKind};
+use crate::error::AnalyzerError;
+use crate::manifest::open_input;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tar::EntryType;
+
+/// Check if a path names a tar archive (`.tar`, `.tar.gz` or `.tgz`)
+pub fn is_tar_archive(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Read the member list of a tar stream into a result
+///
+/// Only headers are read; member contents are skipped, never extracted.
+/// Members are placed under `base`, typically the archive's own path, with
+/// root, prefix and `..` components dropped from their paths; an entry's
+/// depth is its number of remaining components.
+/// Directories are implied by member paths. Symlinks and hard links are
+/// recorded with their link target at their header size.
+pub fn read_tar<R: Read>(reader: R, base: &Path) -> Result<AnalysisResult, AnalyzerError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+
+    for member in archive.entries()? {
+        let member = member?;
+        let header = member.header();
+        let kind = header.entry_type();
+        let is_link = matches!(kind, EntryType::Symlink | EntryType::Link);
+        if !(kind.is_file() || kind.is_gnu_sparse() || is_link) {
+            continue;
+        }
+
+        let path = match member.path() {
+            Ok(path) => path.into_owned(),
+            Err(e) => {
+                warnings.push(format!(
+                    "Unreadable member path in {}: {}",
+                    base.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        // Keep only normal components so a member can't land outside `base`
+        // through `..`, a root or a drive prefix
+        let path: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        let depth = path.components().count();
+        let target = if is_link {
+            member.link_name().ok().flatten().map(|t| t.into_owned())
+        } else {
+            None
+        };
+
+        entries.push(FileEntry {
+            path: base.join(&path),
+            size: member.size(),
+            depth,
+            is_symlink: kind == EntryType::Symlink,
+            target,
+            uid: header.uid().ok().and_then(|uid| u32::try_from(uid).ok()),
+            gid: header.gid().ok().and_then(|gid| u32::try_from(gid).ok()),
+            modified: header
+                .mtime()
+                .ok()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            ..Default::default()
+        });
+    }
+
+    let mut result = AnalysisResult::from_entries(entries);
+    for warning in warnings {
+        result.add_warning(
+            WarningKind::MalformedInput,
+            Some(base.to_path_buf()),
+            warning,
+        );
+    }
+    Ok(result)
+}
+
+/// Read a tar archive, possibly gzipped, see [`read_tar`]
+pub fn load_tar(path: &Path) -> Result<AnalysisResult, AnalyzerError> {
+    read_tar(open_input(path)?, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::{Builder, Header};
+
+    fn tarball() -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        let mut append = |path: &str, kind: EntryType, data: &[u8], link: Option<&str>| {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_700_000_000);
+            if let Some(link) = link {
+                header.set_link_name(link).unwrap();
+            }
+            builder.append_data(&mut header, path, data).unwrap();
+        };
+        append("pkg/", EntryType::Directory, b"", None);
+        append("pkg/a.txt", EntryType::Regular, b"hello", None);
+        append(
+            "pkg/src/lib/b.rs",
+            EntryType::Regular,
+            b"fn main() {}",
+            None,
+        );
+        append("pkg/link", EntryType::Symlink, b"", Some("a.txt"));
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn members_are_read_without_extracting() {
+        let result = read_tar(tarball().as_slice(), Path::new("/archives/pkg.tar")).unwrap();
+
+        let members: Vec<(&Path, u64, usize)> = result
+            .entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.size, e.depth))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                (Path::new("/archives/pkg.tar/pkg/a.txt"), 5, 2),
+                (Path::new("/archives/pkg.tar/pkg/src/lib/b.rs"), 12, 4),
+                (Path::new("/archives/pkg.tar/pkg/link"), 0, 2),
+            ]
+        );
+        assert_eq!(result.file_count, 3);
+        assert_eq!(result.total_size, 17);
+        let link = &result.entries[2];
+        assert!(link.is_symlink);
+        assert_eq!(link.target.as_deref(), Some(Path::new("a.txt")));
+        assert_eq!(
+            result.entries[0].modified,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+    }
+
+    #[test]
+    fn tar_archives_are_recognized_by_name() {
+        for name in ["a.tar", "a.TAR.GZ", "a.tgz"] {
+            assert!(is_tar_archive(Path::new(name)), "{}", name);
+        }
+        assert!(!is_tar_archive(Path::new("a.tar.zst")));
+        assert!(!is_tar_archive(Path::new("a.gz")));
+    }
+}
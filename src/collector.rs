@@ -1,17 +1,88 @@
 // Thread-safe result aggregation
 
-use crate::analyzer::{AnalysisResult, FileEntry};
+use crate::analyzer::{
+    AnalysisResult, DirectoryMtime, FileEntry, ScanMetadata, WarningDetail, WarningKind,
+    size_bucket,
+};
+use crate::observer::ScanObserver;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+/// Histogram whose buckets are bumped atomically
+///
+/// Bumping a bucket that exists only takes a shared lock; the write lock is
+/// needed just to grow the histogram, which happens a handful of times.
+struct AtomicHistogram {
+    buckets: RwLock<Vec<AtomicUsize>>,
+}
+
+impl AtomicHistogram {
+    /// Create a histogram with `len` empty buckets allocated up front
+    fn with_buckets(len: usize) -> Self {
+        Self {
+            buckets: RwLock::new((0..len).map(|_| AtomicUsize::new(0)).collect()),
+        }
+    }
+
+    /// Increment a bucket, growing the histogram as needed
+    fn bump(&self, bucket: usize) {
+        if let Some(count) = self.buckets.read().unwrap().get(bucket) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let mut buckets = self.buckets.write().unwrap();
+        while buckets.len() <= bucket {
+            buckets.push(AtomicUsize::new(0));
+        }
+        buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get the counts, without trailing empty buckets
+    fn counts(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = self
+            .buckets
+            .read()
+            .unwrap()
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        while counts.last() == Some(&0) {
+            counts.pop();
+        }
+        counts
+    }
+}
+
+/// Callback invoked with each file entry as it is collected
+pub type EntrySink = Arc<dyn Fn(&FileEntry) + Send + Sync>;
+
+/// Per-directory counts of files left out for being below the minimum size
+pub type SmallFileCounts = Arc<Mutex<HashMap<PathBuf, usize>>>;
 
 pub struct ResultCollector {
     entries: Arc<Mutex<Vec<FileEntry>>>,
-    warnings: Arc<Mutex<Vec<String>>>,
+    warnings: Arc<Mutex<Vec<(String, WarningDetail)>>>,
     total_size: Arc<AtomicU64>,
+    total_allocated_size: Arc<AtomicU64>,
     file_count: Arc<AtomicUsize>,
     directory_count: Arc<AtomicUsize>,
     symlink_count: Arc<AtomicUsize>,
     incomplete: Arc<AtomicBool>,
+    size_histogram: Arc<AtomicHistogram>,
+    depth_histogram: Arc<AtomicHistogram>,
+    mount_crossings: Arc<Mutex<Vec<PathBuf>>>,
+    truncated_dirs: Arc<Mutex<Vec<PathBuf>>>,
+    dir_mtimes: Arc<Mutex<Vec<DirectoryMtime>>>,
+    directories: Arc<Mutex<Vec<PathBuf>>>,
+    small_file_counts: SmallFileCounts,
+    retain_entries: bool,
+    record_directories: bool,
+    entry_sink: Option<EntrySink>,
+    observers: Vec<Arc<dyn ScanObserver>>,
+    cancel_flag: Option<Arc<AtomicBool>>,
 }
 
 impl Default for ResultCollector {
@@ -26,10 +97,24 @@ impl ResultCollector {
             entries: Arc::new(Mutex::new(Vec::new())),
             warnings: Arc::new(Mutex::new(Vec::new())),
             total_size: Arc::new(AtomicU64::new(0)),
+            total_allocated_size: Arc::new(AtomicU64::new(0)),
             file_count: Arc::new(AtomicUsize::new(0)),
             directory_count: Arc::new(AtomicUsize::new(0)),
             symlink_count: Arc::new(AtomicUsize::new(0)),
             incomplete: Arc::new(AtomicBool::new(false)),
+            // One bucket per possible bit length of a u64 size
+            size_histogram: Arc::new(AtomicHistogram::with_buckets(u64::BITS as usize + 1)),
+            depth_histogram: Arc::new(AtomicHistogram::with_buckets(0)),
+            mount_crossings: Arc::new(Mutex::new(Vec::new())),
+            truncated_dirs: Arc::new(Mutex::new(Vec::new())),
+            dir_mtimes: Arc::new(Mutex::new(Vec::new())),
+            directories: Arc::new(Mutex::new(Vec::new())),
+            small_file_counts: Arc::new(Mutex::new(HashMap::new())),
+            retain_entries: true,
+            record_directories: false,
+            entry_sink: None,
+            observers: Vec::new(),
+            cancel_flag: None,
         }
     }
 
@@ -37,20 +122,114 @@ impl ResultCollector {
     pub fn add_entry(&self, entry: FileEntry) {
         // Update counters
         self.total_size.fetch_add(entry.size, Ordering::Relaxed);
+        if let Some(allocated_size) = entry.allocated_size {
+            self.total_allocated_size
+                .fetch_add(allocated_size, Ordering::Relaxed);
+        }
         self.file_count.fetch_add(1, Ordering::Relaxed);
         if entry.is_symlink {
             self.symlink_count.fetch_add(1, Ordering::Relaxed);
         }
 
+        // Update histograms
+        self.size_histogram.bump(size_bucket(entry.size));
+        self.depth_histogram.bump(entry.depth);
+
+        // Stream to the sink and observers
+        if let Some(ref sink) = self.entry_sink {
+            sink(&entry);
+        }
+        for observer in &self.observers {
+            observer.on_file(&entry);
+        }
+
         // Add to entries list
-        let mut entries = self.entries.lock().unwrap();
-        entries.push(entry);
+        if self.retain_entries {
+            let mut entries = self.entries.lock().unwrap();
+            entries.push(entry);
+        }
+    }
+
+    /// Set whether file entries are retained or only aggregated
+    pub fn set_retain_entries(&mut self, retain: bool) {
+        self.retain_entries = retain;
+    }
+
+    /// Set whether the path of every directory is recorded
+    pub fn set_record_directories(&mut self, record: bool) {
+        self.record_directories = record;
+    }
+
+    /// Set a callback that receives every entry as it is collected
+    pub fn set_entry_sink(&mut self, sink: Option<EntrySink>) {
+        self.entry_sink = sink;
+    }
+
+    /// Set the observers notified of scan events
+    pub fn set_observers(&mut self, observers: Vec<Arc<dyn ScanObserver>>) {
+        self.observers = observers;
+    }
+
+    /// Set a flag that stops the traversal once raised
+    pub fn set_cancel_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.cancel_flag = flag;
+    }
+
+    /// Check if the traversal was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
     }
 
     /// Add a warning message
     pub fn add_warning(&self, warning: String) {
+        self.add_warning_at(WarningKind::Other, None, warning);
+    }
+
+    /// Add a warning message about a path
+    pub fn add_warning_at(&self, kind: WarningKind, path: Option<&Path>, warning: String) {
+        for observer in &self.observers {
+            observer.on_warning(&warning);
+        }
+        let detail = WarningDetail {
+            kind,
+            path: path.map(Path::to_path_buf),
+        };
         let mut warnings = self.warnings.lock().unwrap();
-        warnings.push(warning);
+        warnings.push((warning, detail));
+    }
+
+    /// Record a directory that is on a different device than its parent
+    pub fn add_mount_crossing(&self, path: PathBuf) {
+        let mut crossings = self.mount_crossings.lock().unwrap();
+        crossings.push(path);
+    }
+
+    /// Record a directory whose entries were capped by the per-directory limit
+    pub fn add_truncated_dir(&self, path: PathBuf) {
+        self.add_warning_at(
+            WarningKind::TruncatedDirectory,
+            Some(&path),
+            format!(
+                "Directory has too many entries, only the first were read: {}",
+                path.display()
+            ),
+        );
+        let mut truncated = self.truncated_dirs.lock().unwrap();
+        truncated.push(path);
+    }
+
+    /// Record a directory's modification time
+    pub fn add_dir_mtime(&self, path: PathBuf, modified: SystemTime) {
+        let mut dir_mtimes = self.dir_mtimes.lock().unwrap();
+        dir_mtimes.push(DirectoryMtime { path, modified });
+    }
+
+    /// Get the shared counts of files below the minimum size, for the
+    /// processor to fill
+    pub fn small_file_counts(&self) -> SmallFileCounts {
+        self.small_file_counts.clone()
     }
 
     /// Increment directory count
@@ -58,6 +237,23 @@ impl ResultCollector {
         self.directory_count.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Count a directory about to be read and notify the observers
+    pub fn add_directory(&self, path: &Path, depth: usize) {
+        self.increment_directory_count();
+        if self.record_directories {
+            let mut directories = self.directories.lock().unwrap();
+            directories.push(path.to_path_buf());
+        }
+        for observer in &self.observers {
+            observer.on_dir(path, depth);
+        }
+    }
+
+    /// Get current total size
+    pub fn total_size(&self) -> u64 {
+        self.total_size.load(Ordering::Relaxed)
+    }
+
     /// Get current file count
     pub fn file_count(&self) -> usize {
         self.file_count.load(Ordering::Relaxed)
@@ -75,19 +271,56 @@ impl ResultCollector {
             Err(arc) => arc.lock().unwrap().clone(),
         };
 
-        let warnings = match Arc::try_unwrap(self.warnings) {
+        let (warnings, warning_details) = match Arc::try_unwrap(self.warnings) {
+            Ok(mutex) => mutex.into_inner().unwrap(),
+            Err(arc) => arc.lock().unwrap().clone(),
+        }
+        .into_iter()
+        .unzip();
+
+        let mount_crossings = match Arc::try_unwrap(self.mount_crossings) {
+            Ok(mutex) => mutex.into_inner().unwrap(),
+            Err(arc) => arc.lock().unwrap().clone(),
+        };
+
+        let truncated_dirs = match Arc::try_unwrap(self.truncated_dirs) {
+            Ok(mutex) => mutex.into_inner().unwrap(),
+            Err(arc) => arc.lock().unwrap().clone(),
+        };
+
+        let dir_mtimes = match Arc::try_unwrap(self.dir_mtimes) {
+            Ok(mutex) => mutex.into_inner().unwrap(),
+            Err(arc) => arc.lock().unwrap().clone(),
+        };
+
+        let directories = match Arc::try_unwrap(self.directories) {
+            Ok(mutex) => mutex.into_inner().unwrap(),
+            Err(arc) => arc.lock().unwrap().clone(),
+        };
+
+        let small_file_counts = match Arc::try_unwrap(self.small_file_counts) {
             Ok(mutex) => mutex.into_inner().unwrap(),
             Err(arc) => arc.lock().unwrap().clone(),
         };
 
         AnalysisResult {
             total_size: self.total_size.load(Ordering::Relaxed),
+            total_allocated_size: self.total_allocated_size.load(Ordering::Relaxed),
             file_count: self.file_count.load(Ordering::Relaxed),
             directory_count: self.directory_count.load(Ordering::Relaxed),
             symlink_count: self.symlink_count.load(Ordering::Relaxed),
             entries,
             warnings,
+            warning_details,
             incomplete: self.incomplete.load(Ordering::Relaxed),
+            size_histogram: self.size_histogram.counts(),
+            depth_histogram: self.depth_histogram.counts(),
+            mount_crossings,
+            truncated_dirs,
+            dir_mtimes,
+            directories,
+            small_file_counts: small_file_counts.into_iter().collect(),
+            metadata: ScanMetadata::default(),
         }
     }
 }
@@ -1,19 +1,104 @@
 // Configuration structures for file analysis
 
+use crate::analyzer::FileEntry;
 use crate::error::AnalyzerError;
-use globset::{Glob, GlobSet, GlobSetBuilder};
-use std::path::PathBuf;
+use crate::filesystem::{FileSystem, StdFileSystem};
+use crate::matcher::PatternMatcher;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
 pub struct AnalyzerConfig {
     pub max_depth: Option<usize>,
+    /// Only read the first N entries of each directory
+    pub max_entries_per_dir: Option<usize>,
     pub max_files: Option<usize>,
+    /// Stop scanning once the total size reaches this many bytes
+    pub max_total_size: Option<u64>,
+    /// Whether hitting `max_files`, `max_total_size` or `max_entries_per_dir`
+    /// truncates or fails
+    pub limit_behavior: LimitBehavior,
     pub traversal_strategy: TraversalStrategy,
     pub min_file_size: u64,
+    /// Only include files up to this size
+    pub max_file_size: Option<u64>,
     pub thread_count: usize,
     pub output_path: Option<PathBuf>,
+    /// Leave out this directory, typically the one outputs are written into,
+    /// wherever it lies under a root (a root itself is never left out)
+    pub exclude_output_dir: Option<PathBuf>,
     pub root_path: PathBuf,
-    pub ignore_patterns: Option<GlobSet>,
+    /// Further roots scanned after `root_path` into the same result
+    pub additional_roots: Vec<PathBuf>,
+    pub root_error_policy: RootErrorPolicy,
+    pub ignore_patterns: Option<PatternMatcher>,
+    /// Exact paths skipped during the scan, as reached from a root
+    ///
+    /// `FileAnalyzer` adds the output file here when it lies under a root.
+    pub excluded_paths: Vec<PathBuf>,
+    /// Only count files matching these patterns (directories are still traversed)
+    pub include_patterns: Option<PatternMatcher>,
+    /// Skip paths matched by `.gitignore` files in each root and below,
+    /// see [`GitignoreFilter`](crate::gitignore::GitignoreFilter)
+    pub respect_gitignore: bool,
+    /// Follow Windows shortcuts (.lnk) to their targets (requires the `lnk` feature)
+    pub follow_lnk: bool,
+    /// Add NTFS alternate data stream sizes to each file (requires the `ads` feature)
+    pub include_ads: bool,
+    /// Detect each file's MIME type from its leading bytes (requires the
+    /// `content-type` feature)
+    pub sniff_content_type: bool,
+    /// Only include files whose detected MIME type matches, either exactly
+    /// (`image/png`) or by top-level type (`image/*`); implies sniffing
+    pub content_type_filter: Option<String>,
+    /// Only aggregate sizes into histograms without retaining file entries
+    pub stats_only: bool,
+    pub size_mode: SizeMode,
+    /// Round sizes up to the filesystem cluster size when block info is unavailable
+    pub round_to_cluster: bool,
+    /// Record owning uid/gid per entry (Unix only)
+    pub capture_owner: bool,
+    /// Only include files owned by this uid (Unix only)
+    pub owner: Option<u32>,
+    /// Only include files modified after this time
+    pub modified_after: Option<SystemTime>,
+    /// Only include files modified before this time
+    pub modified_before: Option<SystemTime>,
+    /// Record each entry's canonical path when it differs from the walked path
+    pub record_real_path: bool,
+    /// Record each directory's modification time
+    pub record_dir_mtimes: bool,
+    /// Record the path of every directory entered in the result's
+    /// `directories`
+    pub record_directories: bool,
+    /// Record each file's modification time
+    pub record_file_mtimes: bool,
+    /// Record the process's peak memory use in the result's metadata
+    pub track_peak_memory: bool,
+    /// Count files below `min_file_size` per directory in the result's
+    /// `small_file_counts`; only files passing the other filters are counted,
+    /// and hard links to one file count once
+    pub count_small_files: bool,
+    /// Record symlink target sizes in `target_size`, counting each link by its own size
+    pub symlink_target_sizes: bool,
+    /// Count each symlink target once, however many links reach it
+    pub follow_symlinks_once: bool,
+    /// Never resolve symlinks: record each at its own (lstat) size
+    ///
+    /// Nothing is followed, so cycle tracking is skipped and paths are never
+    /// canonicalized, not even to locate the output file. Conflicts with
+    /// [`AnalyzerConfig::record_real_path`].
+    pub no_symlinks: bool,
+    /// Skip canonical path tracking of directories, assuming the tree has no
+    /// cycles
+    ///
+    /// Saves canonicalizing every directory. Loops are then only caught by
+    /// inode tracking, which is unavailable on Windows, so a cyclic tree can
+    /// be scanned until a depth or file limit stops it.
+    pub trust_no_cycles: bool,
+    /// Seed for randomized features such as sampling, reported in the result's
+    /// metadata when set
+    pub seed: Option<u64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -21,6 +106,8 @@ pub enum TraversalStrategy {
     #[default]
     DepthFirst,
     BreadthFirst,
+    /// Work-stealing traversal on the thread pool; entry order is nondeterministic
+    Parallel,
 }
 
 impl std::str::FromStr for TraversalStrategy {
@@ -30,22 +117,166 @@ impl std::str::FromStr for TraversalStrategy {
         match s.to_lowercase().as_str() {
             "depth-first" | "dfs" | "depth" => Ok(TraversalStrategy::DepthFirst),
             "breadth-first" | "bfs" | "breadth" => Ok(TraversalStrategy::BreadthFirst),
+            "parallel" | "par" => Ok(TraversalStrategy::Parallel),
             _ => Err(format!("Invalid traversal strategy: {}", s)),
         }
     }
 }
 
+/// Which size is reported for each file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeMode {
+    /// Logical file length
+    #[default]
+    Logical,
+    /// Space allocated on disk, or the logical length where it can't be
+    /// determined
+    Allocated,
+}
+
+impl std::str::FromStr for SizeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "logical" | "apparent" => Ok(SizeMode::Logical),
+            "allocated" | "disk" | "on-disk" => Ok(SizeMode::Allocated),
+            _ => Err(format!("Invalid size mode: {}", s)),
+        }
+    }
+}
+
+/// What to do when one of several roots is invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootErrorPolicy {
+    /// Fail the whole analysis
+    #[default]
+    Abort,
+    /// Record a warning and scan the remaining roots
+    Continue,
+}
+
+impl std::str::FromStr for RootErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "abort" => Ok(RootErrorPolicy::Abort),
+            "continue" => Ok(RootErrorPolicy::Continue),
+            _ => Err(format!("Invalid root error policy: {}", s)),
+        }
+    }
+}
+
+/// What to do when a scan limit is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitBehavior {
+    /// Return the partial result marked as incomplete
+    #[default]
+    Truncate,
+    /// Fail with `AnalyzerError::LimitReached`
+    Error,
+}
+
+impl std::str::FromStr for LimitBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "truncate" => Ok(LimitBehavior::Truncate),
+            "error" => Ok(LimitBehavior::Error),
+            _ => Err(format!("Invalid limit behavior: {}", s)),
+        }
+    }
+}
+
+/// Parse a byte size with an optional unit suffix
+///
+/// Plain numbers are bytes. `K`, `M`, `G`, `T` and their `KiB`-style forms
+/// are binary (1024-based); `KB`, `MB`, `GB` and `TB` are decimal
+/// (1000-based). Suffixes are case-insensitive and fractions are allowed,
+/// e.g. `10K`, `1.5M`, `2GB`, `500KiB`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(format!("Invalid size unit '{}' in: {}", unit.trim(), s)),
+    };
+
+    if let Ok(value) = number.parse::<u64>() {
+        return value
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("Size is too large: {}", s));
+    }
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() => {
+            let bytes = (value * multiplier as f64).round();
+            if bytes > u64::MAX as f64 {
+                Err(format!("Size is too large: {}", s))
+            } else {
+                Ok(bytes as u64)
+            }
+        }
+        _ => Err(format!("Invalid size: {}", s)),
+    }
+}
+
 impl AnalyzerConfig {
     pub fn new(root_path: PathBuf) -> Self {
         Self {
             max_depth: None,
+            max_entries_per_dir: None,
             max_files: None,
+            max_total_size: None,
+            limit_behavior: LimitBehavior::default(),
             traversal_strategy: TraversalStrategy::default(),
             min_file_size: 0,
+            max_file_size: None,
             thread_count: num_cpus::get(),
             output_path: None,
+            exclude_output_dir: None,
             root_path,
+            additional_roots: Vec::new(),
+            root_error_policy: RootErrorPolicy::default(),
             ignore_patterns: None,
+            respect_gitignore: false,
+            excluded_paths: Vec::new(),
+            include_patterns: None,
+            follow_lnk: false,
+            include_ads: false,
+            sniff_content_type: false,
+            content_type_filter: None,
+            stats_only: false,
+            size_mode: SizeMode::default(),
+            round_to_cluster: false,
+            capture_owner: false,
+            owner: None,
+            modified_after: None,
+            modified_before: None,
+            symlink_target_sizes: false,
+            record_dir_mtimes: false,
+            record_directories: false,
+            record_file_mtimes: false,
+            record_real_path: false,
+            track_peak_memory: false,
+            count_small_files: false,
+            follow_symlinks_once: false,
+            no_symlinks: false,
+            trust_no_cycles: false,
+            seed: None,
         }
     }
 
@@ -56,46 +287,186 @@ impl AnalyzerConfig {
             return Ok(());
         }
 
-        let mut builder = GlobSetBuilder::new();
-        for pattern in patterns {
-            let glob = Glob::new(&pattern).map_err(|e| {
-                AnalyzerError::InvalidConfig(format!("Invalid glob pattern '{}': {}", pattern, e))
-            })?;
-            builder.add(glob);
+        self.ignore_patterns = Some(PatternMatcher::new(&patterns)?);
+
+        Ok(())
+    }
+
+    /// Set include patterns from a list of glob patterns
+    pub fn set_include_patterns(&mut self, patterns: Vec<String>) -> Result<(), AnalyzerError> {
+        if patterns.is_empty() {
+            self.include_patterns = None;
+            return Ok(());
         }
 
-        self.ignore_patterns = Some(builder.build().map_err(|e| {
-            AnalyzerError::InvalidConfig(format!("Failed to build glob set: {}", e))
-        })?);
+        self.include_patterns = Some(PatternMatcher::new(&patterns)?);
+
+        Ok(())
+    }
+
+    /// Only include files modified after `reference` was, like `find -newer`
+    pub fn set_newer_than(
+        &mut self,
+        reference: &Path,
+        fs: &dyn FileSystem,
+    ) -> Result<(), AnalyzerError> {
+        self.modified_after = Some(Self::reference_mtime(reference, fs)?);
+        Ok(())
+    }
 
+    /// Only include files modified before `reference` was
+    pub fn set_older_than(
+        &mut self,
+        reference: &Path,
+        fs: &dyn FileSystem,
+    ) -> Result<(), AnalyzerError> {
+        self.modified_before = Some(Self::reference_mtime(reference, fs)?);
         Ok(())
     }
 
+    /// Get the modification time of a reference file, following symlinks
+    fn reference_mtime(reference: &Path, fs: &dyn FileSystem) -> Result<SystemTime, AnalyzerError> {
+        fs.metadata(reference)
+            .and_then(|metadata| {
+                metadata.modified.ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::Unsupported, "no mtime recorded")
+                })
+            })
+            .map_err(|e| {
+                AnalyzerError::PathError(format!(
+                    "cannot read mtime of {}: {}",
+                    reference.display(),
+                    e
+                ))
+            })
+    }
+
+    /// Check if a file passes the include patterns
+    pub fn should_include_file(&self, path: &Path) -> bool {
+        self.include_patterns
+            .as_ref()
+            .is_none_or(|patterns| self.matches(patterns, path))
+    }
+
+    /// Check if a file size is within `min_file_size` and `max_file_size`
+    pub fn size_matches(&self, size: u64) -> bool {
+        size >= self.min_file_size && self.max_file_size.is_none_or(|max| size <= max)
+    }
+
+    /// Check if a file's owning uid passes the owner filter
+    pub fn owner_matches(&self, uid: Option<u32>) -> bool {
+        self.owner.is_none_or(|owner| uid == Some(owner))
+    }
+
+    /// Check an entry that was loaded rather than scanned against the filters
+    /// a scan applies
+    ///
+    /// Ignore patterns are checked on the path and every directory above it,
+    /// since a scan never descends into an ignored directory. Include
+    /// patterns, size bounds, the owner and the modification time bounds
+    /// follow.
+    pub fn entry_matches(&self, entry: &FileEntry) -> bool {
+        !entry
+            .path
+            .ancestors()
+            .take_while(|p| !p.as_os_str().is_empty())
+            .any(|p| self.should_ignore(p))
+            && self.should_include_file(&entry.path)
+            && self.size_matches(entry.size)
+            && self.owner_matches(entry.uid)
+            && self.modified_matches(entry.modified)
+    }
+
+    /// Check if a detected MIME type passes the content type filter
+    ///
+    /// Files whose type was not recognized never match a filter.
+    pub fn content_type_matches(&self, content_type: Option<&str>) -> bool {
+        let Some(ref filter) = self.content_type_filter else {
+            return true;
+        };
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        match filter.strip_suffix("/*") {
+            Some(top_level) => content_type
+                .split_once('/')
+                .is_some_and(|(t, _)| t.eq_ignore_ascii_case(top_level)),
+            None => content_type.eq_ignore_ascii_case(filter),
+        }
+    }
+
+    /// Check if a modification time passes the `modified_after` and
+    /// `modified_before` bounds
+    ///
+    /// Both bounds are exclusive, like `find -newer`. Files without a known
+    /// modification time never match a bound.
+    pub fn modified_matches(&self, modified: Option<SystemTime>) -> bool {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let Some(modified) = modified else {
+            return false;
+        };
+        self.modified_after.is_none_or(|after| modified > after)
+            && self.modified_before.is_none_or(|before| modified < before)
+    }
+
+    /// Whether directories are tracked by canonical path to detect cycles
+    /// through symlinks
+    pub fn tracks_visited_paths(&self) -> bool {
+        !self.no_symlinks && !self.trust_no_cycles
+    }
+
     /// Check if a path should be ignored
-    pub fn should_ignore(&self, path: &std::path::Path) -> bool {
+    pub fn should_ignore(&self, path: &Path) -> bool {
+        if self.excluded_paths.iter().any(|excluded| excluded == path) {
+            return true;
+        }
         if let Some(ref patterns) = self.ignore_patterns {
-            patterns.is_match(path)
+            self.matches(patterns, path)
         } else {
             false
         }
     }
 
+    /// Match a path as walked, or relative to the root it descends from
+    ///
+    /// Relative matching lets `build/**` apply under each root of a
+    /// multi-root scan independently.
+    fn matches(&self, patterns: &PatternMatcher, path: &Path) -> bool {
+        patterns.is_match(path)
+            || self.roots().any(|root| {
+                path.strip_prefix(root).is_ok_and(|relative| {
+                    !relative.as_os_str().is_empty() && patterns.is_match(relative)
+                })
+            })
+    }
+
     /// Validate the configuration and return errors if invalid
     pub fn validate(&self) -> Result<(), AnalyzerError> {
-        // Validate root path exists
-        if !self.root_path.exists() {
-            return Err(AnalyzerError::InvalidConfig(format!(
-                "Root path does not exist: {}",
-                self.root_path.display()
-            )));
-        }
+        self.validate_with(&StdFileSystem)
+    }
 
-        // Validate root path is accessible
-        if !self.root_path.is_dir() {
-            return Err(AnalyzerError::InvalidConfig(format!(
-                "Root path is not a directory: {}",
-                self.root_path.display()
-            )));
+    /// Validate the configuration against the given filesystem
+    ///
+    /// With [`RootErrorPolicy::Continue`], invalid roots are only an error
+    /// when no root is valid; the analyzer skips them with a warning.
+    pub fn validate_with(&self, fs: &dyn FileSystem) -> Result<(), AnalyzerError> {
+        match self.root_error_policy {
+            RootErrorPolicy::Abort => {
+                for root in self.roots() {
+                    Self::validate_root(root, fs)?;
+                }
+            }
+            RootErrorPolicy::Continue => {
+                if let Some(Err(e)) = self
+                    .roots()
+                    .map(|root| Self::validate_root(root, fs))
+                    .reduce(|acc, r| acc.or(r))
+                {
+                    return Err(e);
+                }
+            }
         }
 
         // Validate depth is positive if specified
@@ -107,6 +478,20 @@ impl AnalyzerConfig {
             ));
         }
 
+        // Validate per-directory entry limit is positive if specified
+        if self.max_entries_per_dir == Some(0) {
+            return Err(AnalyzerError::InvalidConfig(
+                "Maximum entries per directory must be at least 1".to_string(),
+            ));
+        }
+
+        // Nothing is sniffed without the feature, so a filter would drop every file
+        if cfg!(not(feature = "content-type")) && self.content_type_filter.is_some() {
+            return Err(AnalyzerError::InvalidConfig(
+                "Filtering by content type requires the content-type feature".to_string(),
+            ));
+        }
+
         // Validate thread count is within valid range
         let cpu_count = num_cpus::get();
         if self.thread_count == 0 {
@@ -124,9 +509,148 @@ impl AnalyzerConfig {
         Ok(())
     }
 
+    /// Validate that a root path exists and is a directory
+    pub fn validate_root(root: &Path, fs: &dyn FileSystem) -> Result<(), AnalyzerError> {
+        // Validate root path exists
+        let Ok(root_metadata) = fs.metadata(root) else {
+            return Err(AnalyzerError::InvalidConfig(format!(
+                "Root path does not exist: {}",
+                root.display()
+            )));
+        };
+
+        // Validate root path is accessible
+        if !root_metadata.is_dir() {
+            return Err(AnalyzerError::InvalidConfig(format!(
+                "Root path is not a directory: {}",
+                root.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Iterate over all roots to scan, starting with `root_path`
+    pub fn roots(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.root_path).chain(&self.additional_roots)
+    }
+
     /// Clamp thread count to valid range (1 to CPU count)
     pub fn clamp_thread_count(&mut self) {
         let cpu_count = num_cpus::get();
         self.thread_count = self.thread_count.clamp(1, cpu_count);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFileSystem;
+
+    fn config() -> AnalyzerConfig {
+        AnalyzerConfig::new(PathBuf::from("/data"))
+    }
+
+    #[test]
+    fn owner_filter_needs_a_known_owner() {
+        let mut config = config();
+        assert!(config.owner_matches(None));
+
+        config.owner = Some(1000);
+        assert!(config.owner_matches(Some(1000)));
+        assert!(!config.owner_matches(Some(0)));
+        assert!(!config.owner_matches(None));
+    }
+
+    #[test]
+    fn patterns_match_relative_to_each_root() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/repo/a"));
+        config.additional_roots = vec![PathBuf::from("/work/b")];
+        config
+            .set_ignore_patterns(vec!["build/**".to_string()])
+            .unwrap();
+
+        assert!(config.should_ignore(Path::new("/repo/a/build/out.o")));
+        assert!(config.should_ignore(Path::new("/work/b/build/out.o")));
+        // Only at the top of a root, not deeper
+        assert!(!config.should_ignore(Path::new("/work/b/src/build/out.o")));
+        assert!(!config.should_ignore(Path::new("/elsewhere/build/out.o")));
+    }
+
+    #[test]
+    fn include_patterns_match_relative_to_each_root() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/repo/a"));
+        config.additional_roots = vec![PathBuf::from("/work/b")];
+        config
+            .set_include_patterns(vec!["src/*.rs".to_string()])
+            .unwrap();
+
+        assert!(config.should_include_file(Path::new("/repo/a/src/lib.rs")));
+        assert!(config.should_include_file(Path::new("/work/b/src/main.rs")));
+        assert!(!config.should_include_file(Path::new("/work/b/README.md")));
+    }
+
+    #[test]
+    fn sizes_parse_with_suffixes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10K"), Ok(10 * 1024));
+        assert_eq!(parse_size("10kib"), Ok(10 * 1024));
+        assert_eq!(parse_size("1.5M"), Ok(1536 * 1024));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size(" 3 tb "), Ok(3_000_000_000_000));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected() {
+        assert!(
+            parse_size("10X")
+                .unwrap_err()
+                .contains("Invalid size unit 'X'")
+        );
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("1.2.3K").is_err());
+        assert!(parse_size("-5").is_err());
+        assert!(parse_size("20000000T").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn thread_count_is_clamped_to_the_cpu_count() {
+        let cpus = num_cpus::get();
+        let mut config = config();
+        assert_eq!(config.thread_count, cpus);
+
+        config.thread_count = cpus + 8;
+        config.clamp_thread_count();
+        assert_eq!(config.thread_count, cpus);
+
+        config.thread_count = 0;
+        config.clamp_thread_count();
+        assert_eq!(config.thread_count, 1);
+    }
+
+    #[test]
+    fn zero_threads_fail_validation() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_dir("/data");
+        let mut config = config();
+        config.thread_count = 0;
+        assert!(matches!(
+            config.validate_with(&fs),
+            Err(AnalyzerError::InvalidConfig(_))
+        ));
+    }
+
+    #[cfg(not(feature = "content-type"))]
+    #[test]
+    fn content_type_filter_needs_the_feature() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_dir("/data");
+        let mut config = config();
+        config.content_type_filter = Some("image/*".to_string());
+        assert!(matches!(
+            config.validate_with(&fs),
+            Err(AnalyzerError::InvalidConfig(_))
+        ));
+    }
+}
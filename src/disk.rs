@@ -0,0 +1,312 @@
+// Filesystem device and allocation unit queries
+
+use std::fs::Metadata;
+use std::path::Path;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// Get the allocated (on-disk) size of a file from its block count
+///
+/// Returns `None` on platforms where block information is unavailable.
+#[cfg(unix)]
+pub fn allocated_size(metadata: &Metadata) -> Option<u64> {
+    // st_blocks is always counted in 512-byte units
+    Some(metadata.blocks() * 512)
+}
+
+#[cfg(not(unix))]
+pub fn allocated_size(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Get the id of the device a file resides on
+///
+/// Returns `None` on platforms where the device id is unavailable.
+#[cfg(unix)]
+pub fn device_id(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Get the inode number of a file
+///
+/// Returns `None` on platforms where the inode number is unavailable.
+#[cfg(unix)]
+pub fn inode(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+pub fn inode(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Get the owning user and group ids of a file
+///
+/// Returns `None` on platforms without Unix ownership.
+#[cfg(unix)]
+pub fn owner(metadata: &Metadata) -> Option<(u32, u32)> {
+    Some((metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+pub fn owner(_metadata: &Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+/// Round a logical size up to a multiple of the cluster size
+pub fn round_up_to_cluster(size: u64, cluster_size: u64) -> u64 {
+    if cluster_size == 0 {
+        return size;
+    }
+    size.div_ceil(cluster_size) * cluster_size
+}
+
+/// Query the cluster (allocation unit) size of the filesystem containing a path
+#[cfg(unix)]
+pub fn cluster_size(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is a plain C struct for which all-zero bytes is a valid value
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL-terminated and stat points to a valid statvfs
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    let size = if stat.f_frsize > 0 {
+        stat.f_frsize
+    } else {
+        stat.f_bsize
+    };
+    #[allow(clippy::unnecessary_cast)]
+    let size = size as u64;
+    (size > 0).then_some(size)
+}
+
+/// Query the cluster (allocation unit) size of the volume containing a path
+#[cfg(windows)]
+pub fn cluster_size(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceW;
+
+    let root = volume_root(path)?;
+    let wide: Vec<u16> = root.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut sectors_per_cluster = 0u32;
+    let mut bytes_per_sector = 0u32;
+    let mut free_clusters = 0u32;
+    let mut total_clusters = 0u32;
+
+    // SAFETY: wide is NUL-terminated and all out pointers are valid
+    let ok = unsafe {
+        GetDiskFreeSpaceW(
+            wide.as_ptr(),
+            &mut sectors_per_cluster,
+            &mut bytes_per_sector,
+            &mut free_clusters,
+            &mut total_clusters,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+
+    let size = u64::from(sectors_per_cluster) * u64::from(bytes_per_sector);
+    (size > 0).then_some(size)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn cluster_size(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Total capacity and free space of a filesystem, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub total: u64,
+    /// Space available to the current user
+    pub free: u64,
+}
+
+/// Query the capacity and free space of the filesystem containing a path
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is a plain C struct for which all-zero bytes is a valid value
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL-terminated and stat points to a valid statvfs
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    // Block counts are in units of the fragment size
+    let unit = if stat.f_frsize > 0 {
+        stat.f_frsize
+    } else {
+        stat.f_bsize
+    };
+    #[allow(clippy::unnecessary_cast)]
+    let (unit, blocks, available) = (unit as u64, stat.f_blocks as u64, stat.f_bavail as u64);
+    Some(DiskSpace {
+        total: blocks.saturating_mul(unit),
+        free: available.saturating_mul(unit),
+    })
+}
+
+/// Query the capacity and free space of the volume containing a path
+#[cfg(windows)]
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let root = volume_root(path)?;
+    let wide: Vec<u16> = root.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    let mut total = 0u64;
+    let mut total_free = 0u64;
+
+    // SAFETY: wide is NUL-terminated and all out pointers are valid
+    let ok =
+        unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, &mut total_free) };
+    if ok == 0 {
+        return None;
+    }
+
+    Some(DiskSpace {
+        total,
+        free: available,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn disk_space(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
+/// Sum the sizes of a file's NTFS alternate data streams
+///
+/// The unnamed default stream (`::$DATA`) is excluded since it is already
+/// counted as the file's length. Returns `None` if streams can't be listed.
+#[cfg(all(windows, feature = "ads"))]
+pub fn alternate_streams_size(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+        WIN32_FIND_STREAM_DATA,
+    };
+
+    const DEFAULT_STREAM: &str = "::$DATA";
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut data = WIN32_FIND_STREAM_DATA::default();
+
+    // SAFETY: wide is NUL-terminated and data is a valid WIN32_FIND_STREAM_DATA
+    let handle = unsafe {
+        FindFirstStreamW(
+            wide.as_ptr(),
+            FindStreamInfoStandard,
+            (&mut data as *mut WIN32_FIND_STREAM_DATA).cast(),
+            0,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return None;
+    }
+
+    let mut total = 0u64;
+    loop {
+        let name_len = data
+            .cStreamName
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.cStreamName.len());
+        let name = String::from_utf16_lossy(&data.cStreamName[..name_len]);
+        if name != DEFAULT_STREAM {
+            total += u64::try_from(data.StreamSize).unwrap_or(0);
+        }
+
+        // SAFETY: handle is a valid stream search handle and data is valid
+        let more =
+            unsafe { FindNextStreamW(handle, (&mut data as *mut WIN32_FIND_STREAM_DATA).cast()) };
+        if more == 0 {
+            break;
+        }
+    }
+
+    // SAFETY: handle was returned by FindFirstStreamW and is closed once
+    unsafe { FindClose(handle) };
+
+    Some(total)
+}
+
+/// Get the volume root (e.g. `C:\`) of a path
+#[cfg(windows)]
+fn volume_root(path: &Path) -> Option<std::path::PathBuf> {
+    use std::path::{Component, PathBuf};
+
+    let canonical = path.canonicalize().ok()?;
+    let mut root = PathBuf::new();
+    for component in canonical.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => root.push(component),
+            _ => break,
+        }
+    }
+    Some(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_round_up_to_whole_clusters() {
+        assert_eq!(round_up_to_cluster(5000, 4096), 8192);
+        assert_eq!(round_up_to_cluster(4096, 4096), 4096);
+        assert_eq!(round_up_to_cluster(1, 4096), 4096);
+        assert_eq!(round_up_to_cluster(0, 4096), 0);
+    }
+
+    #[test]
+    fn zero_cluster_size_keeps_size() {
+        assert_eq!(round_up_to_cluster(5000, 0), 5000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cluster_size_is_queried() {
+        assert!(cluster_size(Path::new(".")).is_some_and(|size| size > 0));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn disk_space_is_queried() {
+        let space = disk_space(Path::new(".")).unwrap();
+        assert!(space.total > 0);
+        assert!(space.free <= space.total);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn owner_is_the_creating_user() {
+        let path = std::env::temp_dir().join(format!("wiztree-owner-{}", std::process::id()));
+        std::fs::write(&path, b"x").unwrap();
+        let metadata = std::fs::metadata(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        assert_eq!(owner(&metadata.unwrap()).map(|(uid, _)| uid), Some(uid));
+    }
+}
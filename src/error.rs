@@ -16,6 +16,15 @@ pub enum AnalyzerError {
     #[error("Thread pool error: {0}")]
     ThreadPool(String),
 
+    #[error("Analysis was cancelled")]
+    Cancelled,
+
+    #[error("Limit reached: {0}")]
+    LimitReached(String),
+
+    #[error("Output error: {0}")]
+    Output(String),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 }
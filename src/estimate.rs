@@ -0,0 +1,251 @@
+// Quick estimate of a tree's size before a full scan
+
+use crate::config::AnalyzerConfig;
+use crate::filesystem::FileSystem;
+use crate::link_handler::LinkHandler;
+use crate::sample::SplitMix64;
+use crate::walker::DirectoryWalker;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Maximum number of directories listed by one estimate
+const MAX_LISTED_DIRS: usize = 256;
+
+/// Maximum number of random descents per root
+const MAX_PROBES: usize = 1024;
+
+/// Approximate number of files under the roots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryCountEstimate {
+    /// Estimated number of non-directory entries, comparable with
+    /// `AnalysisResult::file_count` of an unfiltered scan
+    pub entries: u64,
+    /// Whether every directory was listed, making `entries` exact
+    pub exact: bool,
+    /// Number of directories listed to produce the estimate
+    pub listed_dirs: usize,
+    /// Standard error of the estimate relative to it, `None` when exact
+    pub relative_error: Option<f64>,
+}
+
+impl EntryCountEstimate {
+    /// Describe how far the estimate can be trusted
+    pub fn note(&self) -> String {
+        match self.relative_error {
+            None => format!("exact, {} directories listed", self.listed_dirs),
+            Some(error) => {
+                let confidence = if error < 0.25 {
+                    "high"
+                } else if error < 0.75 {
+                    "medium"
+                } else {
+                    "low"
+                };
+                format!(
+                    "{} confidence, ±{:.0}% from {} directories listed",
+                    confidence,
+                    error * 100.0,
+                    self.listed_dirs
+                )
+            }
+        }
+    }
+}
+
+/// Files and subdirectories directly inside one directory
+struct Listing {
+    files: u64,
+    subdirs: Vec<PathBuf>,
+}
+
+/// Estimate the file count of the configured roots
+///
+/// Each root is sampled with random descents from the root to a leaf
+/// (Knuth's estimator): a descent adds each level's file count multiplied by
+/// the product of the branching factors above it, which is an unbiased guess
+/// of the tree's total. Listings are cached and at most [`MAX_LISTED_DIRS`]
+/// directories are read, so the cost is bounded however large the tree is.
+/// Small trees end up fully listed and are counted exactly.
+pub(crate) fn estimate_entry_count(
+    config: &AnalyzerConfig,
+    fs: Arc<dyn FileSystem>,
+    seed: u64,
+) -> EntryCountEstimate {
+    let walker = DirectoryWalker::new(Arc::new(LinkHandler::new(fs.clone())), fs);
+    let mut sampler = Sampler {
+        config,
+        walker,
+        listings: HashMap::new(),
+        rng: SplitMix64::new(seed),
+    };
+
+    let mut entries = 0.0;
+    let mut variance = 0.0;
+    let mut exact = true;
+    for root in config.roots() {
+        if AnalyzerConfig::validate_root(root, sampler.walker.file_system().as_ref()).is_err() {
+            continue;
+        }
+
+        let probes: Vec<f64> = (0..MAX_PROBES).map_while(|_| sampler.probe(root)).collect();
+        if let Some(total) = sampler.exact_count(root) {
+            entries += total as f64;
+        } else if !probes.is_empty() {
+            let n = probes.len() as f64;
+            let mean = probes.iter().sum::<f64>() / n;
+            let spread = probes.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n;
+            entries += mean;
+            variance += spread / n;
+            exact = false;
+        }
+    }
+
+    let relative_error = (!exact).then(|| {
+        if entries > 0.0 {
+            variance.sqrt() / entries
+        } else {
+            1.0
+        }
+    });
+    EntryCountEstimate {
+        entries: entries.round() as u64,
+        exact,
+        listed_dirs: sampler.listings.len(),
+        relative_error,
+    }
+}
+
+struct Sampler<'a> {
+    config: &'a AnalyzerConfig,
+    walker: DirectoryWalker,
+    listings: HashMap<PathBuf, Listing>,
+    rng: SplitMix64,
+}
+
+impl Sampler<'_> {
+    /// Make one random descent from the root, `None` once the listing budget
+    /// is spent
+    fn probe(&mut self, root: &Path) -> Option<f64> {
+        let mut estimate = 0.0;
+        let mut weight = 1.0;
+        let mut dir = root.to_path_buf();
+        let mut depth = 1;
+
+        loop {
+            let listing = self.list(&dir, depth)?;
+            let (files, branches) = (listing.files, listing.subdirs.len());
+            estimate += weight * files as f64;
+            if branches == 0 {
+                return Some(estimate);
+            }
+            weight *= branches as f64;
+            let pick = self.rng.below(branches as u64) as usize;
+            dir = self.listings[&dir].subdirs[pick].clone();
+            depth += 1;
+        }
+    }
+
+    /// Count files exactly when every directory under `dir` has been listed
+    fn exact_count(&self, dir: &Path) -> Option<u64> {
+        let listing = self.listings.get(dir)?;
+        listing
+            .subdirs
+            .iter()
+            .try_fold(listing.files, |total, subdir| {
+                Some(total + self.exact_count(subdir)?)
+            })
+    }
+
+    /// List a directory through the cache, `None` when the budget is spent
+    ///
+    /// Unreadable directories count as empty. Symlinks count as files and
+    /// ignored entries are skipped, as in a scan with default settings.
+    fn list(&mut self, dir: &Path, depth: usize) -> Option<&Listing> {
+        if !self.listings.contains_key(dir) {
+            if self.listings.len() >= MAX_LISTED_DIRS {
+                return None;
+            }
+
+            let mut listing = Listing {
+                files: 0,
+                subdirs: Vec::new(),
+            };
+            let metadata = self.walker.symlink_metadata(dir).ok();
+            if let Some(metadata) = metadata
+                && let Ok(read) = self
+                    .walker
+                    .read_dir(dir, &metadata, depth, self.config.max_depth)
+            {
+                for entry in read.entries {
+                    if self.config.should_ignore(&entry.path) {
+                        continue;
+                    }
+                    if self.walker.should_traverse(&entry, self.config.max_depth) {
+                        listing.subdirs.push(entry.path);
+                    } else if !entry.metadata.is_dir() {
+                        listing.files += 1;
+                    }
+                }
+            }
+            self.listings.insert(dir.to_path_buf(), listing);
+        }
+        self.listings.get(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileAnalyzer, MemoryFileSystem};
+
+    /// Every directory holds 3 files and, above the last level, 8 subdirectories
+    fn uniform_tree(levels: usize) -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        let mut dirs = vec![PathBuf::from("/data")];
+        for level in 0..=levels {
+            let mut next = Vec::new();
+            for dir in &dirs {
+                for file in 0..3 {
+                    fs.add_file(dir.join(format!("f{}.txt", file)), 1);
+                }
+                if level < levels {
+                    next.extend((0..8).map(|sub| dir.join(format!("d{}", sub))));
+                }
+            }
+            dirs = next;
+        }
+        fs
+    }
+
+    fn estimate_and_count(fs: MemoryFileSystem) -> (EntryCountEstimate, usize) {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.seed = Some(7);
+        let analyzer = FileAnalyzer::with_file_system(config, Arc::new(fs));
+        let estimate = analyzer.estimate_entry_count();
+        (estimate, analyzer.analyze().unwrap().file_count)
+    }
+
+    #[test]
+    fn small_trees_are_counted_exactly() {
+        let (estimate, count) = estimate_and_count(uniform_tree(1));
+        assert!(estimate.exact);
+        assert_eq!(estimate.entries, count as u64);
+        assert_eq!(estimate.relative_error, None);
+    }
+
+    #[test]
+    fn large_trees_are_estimated_within_a_factor() {
+        let (estimate, count) = estimate_and_count(uniform_tree(3));
+        assert!(!estimate.exact);
+        assert!(estimate.listed_dirs <= MAX_LISTED_DIRS);
+        let ratio = estimate.entries as f64 / count as f64;
+        assert!(
+            (0.5..=2.0).contains(&ratio),
+            "{} for {}",
+            estimate.entries,
+            count
+        );
+    }
+}
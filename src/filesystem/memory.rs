@@ -0,0 +1,480 @@
+// In-memory filesystem for hermetic tests and benchmarks
+
+use crate::filesystem::{FileKind, FileMetadata, FileSystem};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Maximum number of symlinks followed while resolving a path
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Device id reported for every in-memory entry
+const MEMORY_DEVICE: u64 = 1;
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    File {
+        size: u64,
+        /// Contents when set, otherwise the file reads as `size` zero bytes
+        contents: Option<Vec<u8>>,
+    },
+    Dir {
+        children: BTreeSet<OsString>,
+    },
+    Symlink {
+        target: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    ino: u64,
+    readable: bool,
+    modified: Option<SystemTime>,
+    owner: Option<(u32, u32)>,
+}
+
+/// A filesystem held entirely in memory
+///
+/// Paths are absolute and use `/` as the root. Parent directories are
+/// created automatically when adding entries.
+#[derive(Debug, Clone)]
+pub struct MemoryFileSystem {
+    nodes: HashMap<PathBuf, Node>,
+    next_ino: u64,
+}
+
+impl Default for MemoryFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        let mut fs = Self {
+            nodes: HashMap::new(),
+            next_ino: 1,
+        };
+        fs.insert(
+            PathBuf::from("/"),
+            NodeKind::Dir {
+                children: BTreeSet::new(),
+            },
+        );
+        fs
+    }
+
+    /// Add a regular file with the given size
+    pub fn add_file(&mut self, path: impl AsRef<Path>, size: u64) -> &mut Self {
+        self.insert_at(
+            path.as_ref(),
+            NodeKind::File {
+                size,
+                contents: None,
+            },
+        );
+        self
+    }
+
+    /// Add a regular file holding `contents`
+    pub fn add_file_with_contents(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: impl Into<Vec<u8>>,
+    ) -> &mut Self {
+        let contents = contents.into();
+        self.insert_at(
+            path.as_ref(),
+            NodeKind::File {
+                size: contents.len() as u64,
+                contents: Some(contents),
+            },
+        );
+        self
+    }
+
+    /// Add an empty directory
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        let path = normalize(path.as_ref());
+        if !self.nodes.contains_key(&path) {
+            self.insert_at(
+                &path,
+                NodeKind::Dir {
+                    children: BTreeSet::new(),
+                },
+            );
+        }
+        self
+    }
+
+    /// Add a symlink pointing at `target` (absolute or relative to the link's parent)
+    pub fn add_symlink(&mut self, path: impl AsRef<Path>, target: impl AsRef<Path>) -> &mut Self {
+        self.insert_at(
+            path.as_ref(),
+            NodeKind::Symlink {
+                target: target.as_ref().to_path_buf(),
+            },
+        );
+        self
+    }
+
+    /// Add a hard link to the entry at `original`, sharing its inode number
+    ///
+    /// The link is a copy, so later changes to either entry aren't mirrored.
+    pub fn add_hard_link(
+        &mut self,
+        path: impl AsRef<Path>,
+        original: impl AsRef<Path>,
+    ) -> &mut Self {
+        if let Some(node) = self.nodes.get(&normalize(original.as_ref())).cloned() {
+            self.insert_at(path.as_ref(), node.kind.clone());
+            self.nodes.insert(normalize(path.as_ref()), node);
+        }
+        self
+    }
+
+    /// Make an entry unreadable, so listing or resolving through it fails
+    /// with `PermissionDenied`
+    pub fn deny(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(&normalize(path.as_ref())) {
+            node.readable = false;
+        }
+        self
+    }
+
+    /// Set the modification time reported for an entry
+    pub fn set_modified(&mut self, path: impl AsRef<Path>, modified: SystemTime) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(&normalize(path.as_ref())) {
+            node.modified = Some(modified);
+        }
+        self
+    }
+
+    /// Set the owning user and group ids reported for an entry
+    pub fn set_owner(&mut self, path: impl AsRef<Path>, uid: u32, gid: u32) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(&normalize(path.as_ref())) {
+            node.owner = Some((uid, gid));
+        }
+        self
+    }
+
+    fn insert(&mut self, path: PathBuf, kind: NodeKind) {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.nodes.insert(
+            path,
+            Node {
+                kind,
+                ino,
+                readable: true,
+                modified: None,
+                owner: None,
+            },
+        );
+    }
+
+    fn insert_at(&mut self, path: &Path, kind: NodeKind) {
+        let path = normalize(path);
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return;
+        };
+        let (parent, name) = (parent.to_path_buf(), name.to_os_string());
+
+        self.add_dir(&parent);
+        if let Some(Node {
+            kind: NodeKind::Dir { children },
+            ..
+        }) = self.nodes.get_mut(&parent)
+        {
+            children.insert(name);
+        }
+        self.insert(path, kind);
+    }
+
+    fn node(&self, path: &Path) -> io::Result<&Node> {
+        self.nodes
+            .get(path)
+            .ok_or_else(|| error(io::ErrorKind::NotFound, path))
+    }
+
+    /// Resolve symlinks in every component of a path
+    ///
+    /// The final component is only followed when `follow_last` is set.
+    fn resolve(&self, path: &Path, follow_last: bool) -> io::Result<PathBuf> {
+        let mut pending: Vec<OsString> = normalize(path)
+            .components()
+            .rev()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_os_string()),
+                _ => None,
+            })
+            .collect();
+        let mut resolved = PathBuf::from("/");
+        let mut hops = 0;
+
+        while let Some(name) = pending.pop() {
+            let parent = self.node(&resolved)?;
+            if !parent.readable {
+                return Err(error(io::ErrorKind::PermissionDenied, &resolved));
+            }
+
+            let candidate = resolved.join(&name);
+            let node = self.node(&candidate)?;
+            match &node.kind {
+                NodeKind::Symlink { target } if follow_last || !pending.is_empty() => {
+                    hops += 1;
+                    if hops > MAX_SYMLINK_HOPS {
+                        return Err(io::Error::other(format!(
+                            "Too many levels of symbolic links: {}",
+                            path.display()
+                        )));
+                    }
+
+                    // Restart resolution from the link target
+                    let target = normalize(&resolved.join(target));
+                    pending.extend(target.components().rev().filter_map(|c| match c {
+                        Component::Normal(name) => Some(name.to_os_string()),
+                        _ => None,
+                    }));
+                    resolved = PathBuf::from("/");
+                }
+                _ => resolved = candidate,
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn metadata_of(&self, node: &Node) -> FileMetadata {
+        let (kind, len) = match &node.kind {
+            NodeKind::File { size, .. } => (FileKind::File, *size),
+            NodeKind::Dir { .. } => (FileKind::Dir, 0),
+            NodeKind::Symlink { target } => (FileKind::Symlink, target.as_os_str().len() as u64),
+        };
+
+        FileMetadata {
+            kind,
+            len,
+            allocated_size: None,
+            dev: Some(MEMORY_DEVICE),
+            ino: Some(node.ino),
+            owner: node.owner,
+            modified: node.modified,
+        }
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileMetadata)>> {
+        let resolved = self.resolve(path, true)?;
+        let node = self.node(&resolved)?;
+        let NodeKind::Dir { children } = &node.kind else {
+            return Err(error(io::ErrorKind::NotADirectory, path));
+        };
+        if !node.readable {
+            return Err(error(io::ErrorKind::PermissionDenied, path));
+        }
+
+        let mut entries = Vec::with_capacity(children.len());
+        for name in children {
+            let child = self.node(&resolved.join(name))?;
+            entries.push((path.join(name), self.metadata_of(child)));
+        }
+        Ok(entries)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let resolved = self.resolve(path, false)?;
+        Ok(self.metadata_of(self.node(&resolved)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let resolved = self.resolve(path, true)?;
+        Ok(self.metadata_of(self.node(&resolved)?))
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path, false)?;
+        match &self.node(&resolved)?.kind {
+            NodeKind::Symlink { target } => Ok(target.clone()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Not a symlink: {}", path.display()),
+            )),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.resolve(path, true)
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let node = self.node(&self.resolve(path, true)?)?;
+        let NodeKind::File { size, contents } = &node.kind else {
+            return Err(error(io::ErrorKind::IsADirectory, path));
+        };
+        if !node.readable {
+            return Err(error(io::ErrorKind::PermissionDenied, path));
+        }
+
+        Ok(match contents {
+            Some(contents) => contents[..len.min(contents.len())].to_vec(),
+            None => vec![0; len.min(*size as usize)],
+        })
+    }
+}
+
+/// Build an IO error that names the offending path
+fn error(kind: io::ErrorKind, path: &Path) -> io::Error {
+    io::Error::new(kind, format!("{}: {}", kind, path.display()))
+}
+
+/// Lexically normalize a path to an absolute path without `.` or `..`
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::WarningKind;
+    use crate::test_support::scan;
+    use crate::{AnalyzerConfig, TraversalStrategy};
+
+    fn tree() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/a.txt", 10)
+            .add_file("/data/src/b.rs", 20)
+            .add_file("/data/src/deep/c.rs", 30)
+            .add_dir("/data/empty");
+        fs
+    }
+
+    #[test]
+    fn parents_are_created_and_listed() {
+        let fs = tree();
+        let mut names: Vec<PathBuf> = fs
+            .read_dir(Path::new("/data"))
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["/data/a.txt", "/data/empty", "/data/src"].map(PathBuf::from)
+        );
+        assert!(fs.metadata(Path::new("/data/src/deep")).unwrap().is_dir());
+    }
+
+    #[test]
+    fn symlinks_resolve_through_directories() {
+        let mut fs = tree();
+        fs.add_symlink("/data/link", "src");
+        assert_eq!(
+            fs.canonicalize(Path::new("/data/link/deep/c.rs")).unwrap(),
+            Path::new("/data/src/deep/c.rs")
+        );
+        assert_eq!(
+            fs.read_link(Path::new("/data/link")).unwrap(),
+            Path::new("src")
+        );
+        assert!(
+            fs.symlink_metadata(Path::new("/data/link"))
+                .unwrap()
+                .is_symlink()
+        );
+    }
+
+    #[test]
+    fn denied_directories_fail_to_list() {
+        let mut fs = tree();
+        fs.deny("/data/src");
+        let error = fs.read_dir(Path::new("/data/src")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn heads_are_read_from_contents_or_zeros() {
+        let mut fs = tree();
+        fs.add_file_with_contents("/data/hello.txt", "hello")
+            .add_symlink("/data/hi", "hello.txt")
+            .add_file_with_contents("/data/src/secret", "x")
+            .deny("/data/src/secret");
+
+        assert_eq!(fs.read_head(Path::new("/data/hi"), 4).unwrap(), b"hell");
+        assert_eq!(
+            fs.read_head(Path::new("/data/hello.txt"), 64).unwrap(),
+            b"hello"
+        );
+        assert_eq!(fs.read_head(Path::new("/data/a.txt"), 4).unwrap(), [0; 4]);
+        assert_eq!(fs.metadata(Path::new("/data/hello.txt")).unwrap().len(), 5);
+        assert!(fs.read_head(Path::new("/data/src"), 4).is_err());
+        let error = fs.read_head(Path::new("/data/src/secret"), 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn analyzer_traverses_memory_tree() {
+        for strategy in [
+            TraversalStrategy::DepthFirst,
+            TraversalStrategy::BreadthFirst,
+            TraversalStrategy::Parallel,
+        ] {
+            let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+            config.traversal_strategy = strategy;
+            let result = scan(config, tree());
+
+            assert_eq!(result.file_count, 3, "{:?}", strategy);
+            assert_eq!(result.total_size, 60, "{:?}", strategy);
+            assert!(result.warnings.is_empty(), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn circular_symlinks_end_the_scan_with_a_warning() {
+        let mut fs = tree();
+        fs.add_symlink("/data/src/loop", "/data");
+        for strategy in [
+            TraversalStrategy::DepthFirst,
+            TraversalStrategy::BreadthFirst,
+            TraversalStrategy::Parallel,
+        ] {
+            let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+            config.traversal_strategy = strategy;
+            let result = scan(config, fs.clone());
+
+            assert_eq!(result.file_count, 3, "{:?}", strategy);
+            assert_eq!(result.total_size, 60, "{:?}", strategy);
+            assert_eq!(
+                result.warnings,
+                ["Circular symlink detected: /data/src/loop"],
+                "{:?}",
+                strategy
+            );
+            assert!(
+                result
+                    .warning_details
+                    .iter()
+                    .any(|d| d.kind == WarningKind::SymlinkCycle
+                        && d.path.as_deref() == Some(Path::new("/data/src/loop"))),
+                "{:?}",
+                strategy
+            );
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// Filesystem abstraction used by the walker, processor and link handler
+
+use crate::disk::{self, DiskSpace};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub mod memory;
+pub use memory::MemoryFileSystem;
+
+/// Kind of a filesystem entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// Platform-independent subset of file metadata used by the analyzer
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    pub kind: FileKind,
+    pub len: u64,
+    /// On-disk size, when block information is available
+    pub allocated_size: Option<u64>,
+    /// Device id, when available
+    pub dev: Option<u64>,
+    /// Inode number (or equivalent), when available
+    pub ino: Option<u64>,
+    /// Owning user and group ids, when available
+    pub owner: Option<(u32, u32)>,
+    /// Last modification time, when available
+    pub modified: Option<SystemTime>,
+}
+
+impl FileMetadata {
+    pub fn from_std(metadata: &fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        };
+
+        Self {
+            kind,
+            len: metadata.len(),
+            allocated_size: disk::allocated_size(metadata),
+            dev: disk::device_id(metadata),
+            ino: disk::inode(metadata),
+            owner: disk::owner(metadata),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileKind::Symlink
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Filesystem operations needed for traversal
+///
+/// The analyzer uses [`StdFileSystem`] by default. Alternative implementations
+/// such as [`MemoryFileSystem`] allow hermetic tests and benchmarks that never
+/// touch the disk.
+pub trait FileSystem: Send + Sync {
+    /// List a directory's entries, skipping entries that cannot be read
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileMetadata)>>;
+
+    /// List at most `limit` of a directory's entries
+    ///
+    /// Implementations should stop reading once the limit is reached, so huge
+    /// directories are never fully listed.
+    fn read_dir_limited(
+        &self,
+        path: &Path,
+        limit: usize,
+    ) -> io::Result<Vec<(PathBuf, FileMetadata)>> {
+        let mut entries = self.read_dir(path)?;
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    /// Get metadata without following a final symlink
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Get metadata, following symlinks
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Read the target of a symlink
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Resolve a path to its canonical form, following all symlinks
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Read up to `len` bytes from the start of a file, following symlinks
+    ///
+    /// Used to read `.gitignore` files and sniff content types. Fails with
+    /// `Unsupported` by default.
+    fn read_head(&self, path: &Path, _len: usize) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Cannot read {}", path.display()),
+        ))
+    }
+
+    /// Get the capacity and free space of the volume holding a path
+    ///
+    /// Returns `None` when the filesystem has no notion of capacity.
+    fn disk_space(&self, _path: &Path) -> Option<DiskSpace> {
+        None
+    }
+}
+
+/// Filesystem backed by `std::fs`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileMetadata)>> {
+        self.read_dir_limited(path, usize::MAX)
+    }
+
+    fn read_dir_limited(
+        &self,
+        path: &Path,
+        limit: usize,
+    ) -> io::Result<Vec<(PathBuf, FileMetadata)>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path)? {
+            if entries.len() >= limit {
+                break;
+            }
+
+            let entry = match entry {
+                Ok(e) => e,
+                Err(_) => continue, // Skip entries we can't read
+            };
+
+            let metadata = match entry.metadata() {
+                Ok(m) => m,
+                Err(_) => continue, // Skip entries we can't get metadata for
+            };
+
+            entries.push((entry.path(), FileMetadata::from_std(&metadata)));
+        }
+        Ok(entries)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        fs::symlink_metadata(path).map(|m| FileMetadata::from_std(&m))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        fs::metadata(path).map(|m| FileMetadata::from_std(&m))
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let mut head = Vec::with_capacity(len);
+        fs::File::open(path)?
+            .take(len as u64)
+            .read_to_end(&mut head)?;
+        Ok(head)
+    }
+
+    fn disk_space(&self, path: &Path) -> Option<DiskSpace> {
+        disk::disk_space(path)
+    }
+}
@@ -0,0 +1,130 @@
+// Skipping paths matched by .gitignore files
+
+use crate::filesystem::FileSystem;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Name of the per-directory ignore file
+const GITIGNORE: &str = ".gitignore";
+
+/// Longest `.gitignore` read; anything after this is ignored
+const MAX_GITIGNORE_LEN: usize = 1 << 20;
+
+/// Matches paths against the `.gitignore` files of a scan root
+///
+/// Each directory's `.gitignore` is read through the [`FileSystem`] the
+/// first time a path below it is checked. Rules of deeper files take
+/// precedence, and `!` negations re-include paths, as in git. Only files
+/// in the root and below are read, not those of enclosing directories,
+/// `.git/info/exclude` or the global excludes file. Lines that aren't
+/// valid patterns are skipped.
+pub struct GitignoreFilter {
+    root: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    /// Parsed rules per directory, `None` when it has no `.gitignore`
+    rules: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl GitignoreFilter {
+    pub fn new(root: &Path, fs: Arc<dyn FileSystem>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            fs,
+            rules: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Check if a path below the root is ignored
+    ///
+    /// Contents of ignored directories aren't checked separately: the
+    /// traversal never enters them.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for dir in path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+        {
+            if let Some(rules) = self.rules(dir) {
+                match rules.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+        false
+    }
+
+    /// Get a directory's rules, reading its `.gitignore` on first use
+    fn rules(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(rules) = self.rules.read().unwrap().get(dir) {
+            return rules.clone();
+        }
+
+        let rules = self.load(dir).map(Arc::new);
+        self.rules
+            .write()
+            .unwrap()
+            .insert(dir.to_path_buf(), rules.clone());
+        rules
+    }
+
+    fn load(&self, dir: &Path) -> Option<Gitignore> {
+        let contents = self
+            .fs
+            .read_head(&dir.join(GITIGNORE), MAX_GITIGNORE_LEN)
+            .ok()?;
+        let mut builder = GitignoreBuilder::new(dir);
+        for line in String::from_utf8_lossy(&contents).lines() {
+            let _ = builder.add_line(None, line);
+        }
+        builder.build().ok().filter(|rules| !rules.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFileSystem;
+
+    fn filter() -> GitignoreFilter {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file_with_contents(
+            "/repo/.gitignore",
+            "# build output\n/target\n*.log\n!keep.log\n",
+        )
+        .add_file_with_contents("/repo/sub/.gitignore", "*.tmp\n!debug.log\n")
+        .add_file_with_contents("/repo/.gitignore.bak", "*");
+        GitignoreFilter::new(Path::new("/repo"), Arc::new(fs))
+    }
+
+    #[test]
+    fn rules_apply_below_their_directory() {
+        let filter = filter();
+        let ignored = |path: &str, is_dir| filter.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("/repo/target", true));
+        // Anchored to the root's .gitignore
+        assert!(!ignored("/repo/sub/target", true));
+        assert!(ignored("/repo/a.log", false));
+        assert!(ignored("/repo/sub/deep/b.log", false));
+        assert!(!ignored("/repo/keep.log", false));
+        assert!(ignored("/repo/sub/c.tmp", false));
+        assert!(!ignored("/repo/c.tmp", false));
+        // Deeper files take precedence
+        assert!(!ignored("/repo/sub/debug.log", false));
+        assert!(!ignored("/repo/src/main.rs", false));
+    }
+
+    #[test]
+    fn rules_outside_the_root_are_not_read() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file_with_contents("/repo/.gitignore", "*.rs\n")
+            .add_file("/repo/sub/main.rs", 1);
+        let filter = GitignoreFilter::new(Path::new("/repo/sub"), Arc::new(fs));
+        assert!(!filter.is_ignored(Path::new("/repo/sub/main.rs"), false));
+    }
+}
@@ -20,7 +20,7 @@
 //! ## Example
 //!
 //! ```no_run
-//! use file_analyzer::{AnalyzerConfig, FileAnalyzer, TraversalStrategy};
+//! use wiztree_metafile::{AnalyzerConfig, FileAnalyzer, TraversalStrategy};
 //! use std::path::PathBuf;
 //!
 //! let mut config = AnalyzerConfig::new(PathBuf::from("."));
@@ -39,17 +39,40 @@
 //! ```
 
 pub mod analyzer;
+#[cfg(feature = "tar")]
+pub mod archive;
 pub mod collector;
 pub mod config;
+pub mod disk;
 pub mod error;
+pub mod estimate;
+pub mod filesystem;
+pub mod gitignore;
 pub mod link_handler;
+pub mod manifest;
+pub mod matcher;
+pub mod memory;
+pub mod observer;
 pub mod output;
 pub mod processor;
+pub mod profile;
+pub mod rollup;
+pub mod sample;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod traversal;
 pub mod walker;
 
 // Re-export main types for convenience
-pub use analyzer::{AnalysisResult, FileAnalyzer, FileEntry};
-pub use config::{AnalyzerConfig, TraversalStrategy};
+pub use analyzer::{
+    AnalysisResult, DirectoryMtime, EntryDiff, ExtensionStats, FileAnalyzer, FileEntry,
+    PathLengthLimit, PrefixSize, ScanMetadata, SmallFileHotspot, WarningDetail, WarningKind,
+};
+pub use config::{AnalyzerConfig, LimitBehavior, RootErrorPolicy, SizeMode, TraversalStrategy};
 pub use error::AnalyzerError;
+pub use estimate::EntryCountEstimate;
+pub use filesystem::{FileSystem, MemoryFileSystem, StdFileSystem};
+pub use observer::{ProgressCallback, ScanObserver, ScanProgress};
 pub use output::OutputFormat;
+pub use profile::{Profile, ProfileOptions};
+pub use rollup::{RollupAccumulator, RollupTotals};
@@ -1,124 +1,169 @@
 // Symbolic link detection and handling
 
+use crate::analyzer::lexical_absolute;
 use crate::error::AnalyzerError;
+use crate::filesystem::{FileMetadata, FileSystem, StdFileSystem};
 use std::collections::HashSet;
-use std::fs::Metadata;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-#[cfg(unix)]
-use std::os::unix::fs::MetadataExt;
+/// Maximum number of symlinks followed when resolving a path lexically
+const MAX_LEXICAL_HOPS: usize = 40;
 
 /// Handles symbolic link detection and circular reference prevention
 pub struct LinkHandler {
     visited_inodes: Arc<Mutex<HashSet<FileId>>>,
     visited_paths: Arc<Mutex<HashSet<PathBuf>>>,
+    visited_dirs: Arc<Mutex<HashSet<FileId>>>,
+    visited_targets: Arc<Mutex<HashSet<PathBuf>>>,
+    fs: Arc<dyn FileSystem>,
 }
 
 /// Platform-independent file identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct FileId {
-    #[cfg(unix)]
     dev: u64,
-    #[cfg(unix)]
     ino: u64,
-    #[cfg(windows)]
-    volume_serial: u32,
-    #[cfg(windows)]
-    file_index: u64,
 }
 
 impl FileId {
-    #[cfg(unix)]
-    fn from_metadata(metadata: &Metadata) -> Self {
-        Self {
-            dev: metadata.dev(),
-            ino: metadata.ino(),
-        }
-    }
-
-    #[cfg(windows)]
-    fn from_metadata(_metadata: &Metadata) -> Option<Self> {
-        // On Windows, file index requires unstable features
-        // For now, we'll disable duplicate detection on Windows
+    fn from_metadata(metadata: &FileMetadata) -> Option<Self> {
+        // On Windows, file index requires unstable features, so the metadata
+        // carries no device/inode and duplicate detection is disabled
         // TODO: Implement when windows_by_handle is stabilized
-        None
+        Some(Self {
+            dev: metadata.dev?,
+            ino: metadata.ino?,
+        })
     }
 }
 
 impl Default for LinkHandler {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(StdFileSystem))
     }
 }
 
 impl LinkHandler {
-    pub fn new() -> Self {
+    pub fn new(fs: Arc<dyn FileSystem>) -> Self {
         Self {
             visited_inodes: Arc::new(Mutex::new(HashSet::new())),
             visited_paths: Arc::new(Mutex::new(HashSet::new())),
+            visited_dirs: Arc::new(Mutex::new(HashSet::new())),
+            visited_targets: Arc::new(Mutex::new(HashSet::new())),
+            fs,
+        }
+    }
+
+    /// Get the key a directory is tracked under: its canonical path, or its
+    /// lexical key when it can't be canonicalized (e.g. an unreadable
+    /// symlink target)
+    fn visit_key(&self, path: &Path) -> Result<PathBuf, (PathBuf, io::Error)> {
+        self.fs
+            .canonicalize(path)
+            .map_err(|e| (self.lexical_key(path), e))
+    }
+
+    /// Resolve a path as far as possible without canonicalizing: make it
+    /// absolute lexically and follow a final symlink for as long as its
+    /// target can be read
+    fn lexical_key(&self, path: &Path) -> PathBuf {
+        let mut key = lexical_absolute(path);
+        for _ in 0..MAX_LEXICAL_HOPS {
+            let Ok(target) = self.fs.read_link(&key) else {
+                break;
+            };
+            key = match key.parent() {
+                Some(parent) => lexical_absolute(&parent.join(target)),
+                None => lexical_absolute(&target),
+            };
         }
+        key
     }
 
     /// Check if a directory path would create a circular reference
+    ///
+    /// Falls back to the lexical path when canonicalizing fails, so the
+    /// check degrades instead of failing.
     pub fn is_circular(&self, path: &Path) -> Result<bool, AnalyzerError> {
-        let canonical = path.canonicalize().map_err(|e| {
-            AnalyzerError::PathError(format!(
-                "Failed to canonicalize path {}: {}",
-                path.display(),
-                e
-            ))
-        })?;
-
+        let key = self.visit_key(path).unwrap_or_else(|(lexical, _)| lexical);
         let visited = self.visited_paths.lock().unwrap();
-        Ok(visited.contains(&canonical))
+        Ok(visited.contains(&key))
     }
 
     /// Mark a directory path as visited to detect circular references
+    ///
+    /// When canonicalizing fails, the lexical path is marked instead and
+    /// the failure is returned so it can be reported as a warning.
     pub fn mark_visited(&self, path: &Path) -> Result<(), AnalyzerError> {
-        let canonical = path.canonicalize().map_err(|e| {
-            AnalyzerError::PathError(format!(
-                "Failed to canonicalize path {}: {}",
+        let (key, error) = match self.visit_key(path) {
+            Ok(canonical) => (canonical, None),
+            Err((lexical, e)) => (lexical, Some(e)),
+        };
+
+        self.visited_paths.lock().unwrap().insert(key);
+
+        match error {
+            Some(e) => Err(AnalyzerError::PathError(format!(
+                "Failed to canonicalize path {}, tracking it lexically: {}",
                 path.display(),
                 e
-            ))
-        })?;
-
-        let mut visited = self.visited_paths.lock().unwrap();
-        visited.insert(canonical);
-        Ok(())
+            ))),
+            None => Ok(()),
+        }
     }
 
-    /// Check if a file has already been counted (duplicate inode)
-    pub fn is_duplicate_inode(&self, metadata: &Metadata) -> bool {
-        #[cfg(unix)]
-        {
-            let file_id = FileId::from_metadata(metadata);
-            let mut visited = self.visited_inodes.lock().unwrap();
-            !visited.insert(file_id)
+    /// Record a directory's inode before entering it
+    ///
+    /// Returns `false` if a directory with the same inode was already entered,
+    /// which indicates a cycle (e.g. directory hardlinks or bind mounts) that
+    /// path-based detection can miss. Directories without an inode are always
+    /// entered.
+    pub fn enter_directory(&self, metadata: &FileMetadata) -> bool {
+        match FileId::from_metadata(metadata) {
+            Some(file_id) => {
+                let mut visited = self.visited_dirs.lock().unwrap();
+                visited.insert(file_id)
+            }
+            None => true,
         }
+    }
 
-        #[cfg(windows)]
-        {
-            if let Some(file_id) = FileId::from_metadata(metadata) {
+    /// Check if a file has already been counted (duplicate inode)
+    pub fn is_duplicate_inode(&self, metadata: &FileMetadata) -> bool {
+        match FileId::from_metadata(metadata) {
+            Some(file_id) => {
                 let mut visited = self.visited_inodes.lock().unwrap();
                 !visited.insert(file_id)
-            } else {
-                // If we can't get file index, assume it's not a duplicate
-                false
             }
+            // If we can't identify the file, assume it's not a duplicate
+            None => false,
         }
+    }
 
-        #[cfg(not(any(unix, windows)))]
-        {
-            // On other platforms, we can't detect duplicates
-            false
+    /// Check if a symlink's target has already been counted
+    ///
+    /// `metadata` is the target's metadata. The target is identified by its
+    /// inode, shared with hard link detection so a target reached directly
+    /// also counts once. Without an inode, its canonical path is tracked
+    /// among symlink targets instead.
+    pub fn is_duplicate_target(&self, path: &Path, metadata: &FileMetadata) -> bool {
+        if FileId::from_metadata(metadata).is_some() {
+            return self.is_duplicate_inode(metadata);
+        }
+        match self.fs.canonicalize(path) {
+            Ok(canonical) => {
+                let mut visited = self.visited_targets.lock().unwrap();
+                !visited.insert(canonical)
+            }
+            Err(_) => false,
         }
     }
 
     /// Resolve a symbolic link to its target
     pub fn resolve_link(&self, path: &Path) -> Result<PathBuf, AnalyzerError> {
-        std::fs::read_link(path).map_err(|e| {
+        self.fs.read_link(path).map_err(|e| {
             AnalyzerError::PathError(format!(
                 "Failed to resolve symlink {}: {}",
                 path.display(),
@@ -126,4 +171,194 @@ impl LinkHandler {
             ))
         })
     }
+
+    /// Check if a path looks like a Windows shortcut (.lnk) file
+    pub fn is_shortcut(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"))
+    }
+
+    /// Resolve a Windows shortcut (.lnk) file to its target
+    #[cfg(all(windows, feature = "lnk"))]
+    pub fn resolve_shortcut(&self, path: &Path) -> Result<PathBuf, AnalyzerError> {
+        let shortcut = lnk::ShellLink::open(path, encoding_rs::WINDOWS_1252).map_err(|e| {
+            AnalyzerError::PathError(format!(
+                "Failed to parse shortcut {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        shortcut.link_target().map(PathBuf::from).ok_or_else(|| {
+            AnalyzerError::PathError(format!("Shortcut has no target: {}", path.display()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FileSystemHooks, WrappedFileSystem, scan};
+    use crate::{AnalysisResult, AnalyzerConfig, FileAnalyzer, MemoryFileSystem, WarningKind};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Makes some directories report another directory's inode, like a
+    /// directory hardlink, makes paths resolving under some directories
+    /// impossible to canonicalize, and counts `canonicalize` calls
+    #[derive(Default)]
+    struct LinkHooks {
+        aliases: HashMap<PathBuf, PathBuf>,
+        uncanonicalizable: Vec<PathBuf>,
+        canonicalize_calls: AtomicUsize,
+    }
+
+    impl FileSystemHooks for LinkHooks {
+        fn remap(&self, inner: &MemoryFileSystem, path: &Path, metadata: &mut FileMetadata) {
+            if let Some(original) = self.aliases.get(path)
+                && let Ok(original) = inner.metadata(original)
+            {
+                metadata.ino = original.ino;
+            }
+        }
+
+        fn canonicalize(&self, inner: &MemoryFileSystem, path: &Path) -> io::Result<PathBuf> {
+            self.canonicalize_calls.fetch_add(1, Ordering::Relaxed);
+            let canonical = inner.canonicalize(path)?;
+            if self
+                .uncanonicalizable
+                .iter()
+                .any(|dir| canonical.starts_with(dir))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("cannot canonicalize {}", path.display()),
+                ));
+            }
+            Ok(canonical)
+        }
+    }
+
+    type TestFileSystem = WrappedFileSystem<LinkHooks>;
+
+    /// Scan and count the `canonicalize` calls made along the way
+    fn scan_counting(config: AnalyzerConfig, fs: TestFileSystem) -> (AnalysisResult, usize) {
+        let fs = Arc::new(fs);
+        let result = FileAnalyzer::with_file_system(config, fs.clone())
+            .analyze()
+            .unwrap();
+        (result, fs.hooks.canonicalize_calls.load(Ordering::Relaxed))
+    }
+
+    fn linked_tree() -> TestFileSystem {
+        let mut fs = TestFileSystem::default();
+        fs.inner
+            .add_file("/data/a.txt", 1)
+            .add_file("/data/sub/b.txt", 2)
+            .add_file("/data/sub/deep/c.txt", 4)
+            .add_symlink("/data/sub/link", "../a.txt");
+        fs
+    }
+
+    #[test]
+    fn directory_hardlink_cycle_is_skipped() {
+        let mut fs = TestFileSystem::default();
+        fs.inner
+            .add_file("/data/a.txt", 1)
+            .add_file("/data/sub/loop/b.txt", 2);
+        fs.hooks
+            .aliases
+            .insert(PathBuf::from("/data/sub/loop"), PathBuf::from("/data"));
+
+        let result = scan(AnalyzerConfig::new(PathBuf::from("/data")), fs);
+
+        assert_eq!(result.total_size, 1);
+        assert_eq!(
+            result.warnings,
+            ["Directory cycle detected (inode already visited): /data/sub/loop"]
+        );
+        assert_eq!(result.warning_details.len(), 1);
+        assert_eq!(result.warning_details[0].kind, WarningKind::DirectoryCycle);
+        assert_eq!(
+            result.warning_details[0].path.as_deref(),
+            Some(Path::new("/data/sub/loop"))
+        );
+    }
+
+    #[test]
+    fn unresolvable_paths_are_tracked_lexically() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_dir("/secret/dir").deny("/secret");
+        let handler = LinkHandler::new(Arc::new(fs));
+
+        let path = Path::new("/secret/dir");
+        assert!(!handler.is_circular(path).unwrap());
+        assert!(matches!(
+            handler.mark_visited(path),
+            Err(AnalyzerError::PathError(_))
+        ));
+        assert!(handler.is_circular(path).unwrap());
+    }
+
+    #[test]
+    fn symlink_to_an_uncanonicalizable_directory_is_tracked_lexically() {
+        let mut fs = TestFileSystem::default();
+        fs.inner
+            .add_file("/data/a.txt", 1)
+            .add_file("/data/share/b.txt", 2)
+            .add_symlink("/data/share/back", "../share");
+        fs.hooks
+            .uncanonicalizable
+            .push(PathBuf::from("/data/share"));
+
+        let result = scan(AnalyzerConfig::new(PathBuf::from("/data")), fs);
+
+        assert_eq!(result.total_size, 3);
+        assert!(!result.incomplete);
+        // Entering the directory warns that it is tracked lexically
+        assert!(result.warning_details.iter().any(|d| {
+            d.kind == WarningKind::Other && d.path.as_deref() == Some(Path::new("/data/share"))
+        }));
+        // The link back into it resolves to the same key
+        assert!(result.warning_details.iter().any(|d| {
+            d.kind == WarningKind::SymlinkCycle
+                && d.path.as_deref() == Some(Path::new("/data/share/back"))
+        }));
+    }
+
+    #[test]
+    fn no_symlinks_never_canonicalizes() {
+        let (_, calls) = scan_counting(AnalyzerConfig::new(PathBuf::from("/data")), linked_tree());
+        assert!(calls > 0);
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.no_symlinks = true;
+        config.record_real_path = true;
+        let (result, calls) = scan_counting(config, linked_tree());
+
+        assert_eq!(calls, 0);
+        assert_eq!(result.file_count, 4);
+        // The link is recorded at its own size, the length of its target path
+        let link = result
+            .entries
+            .iter()
+            .find(|e| e.path == Path::new("/data/sub/link"))
+            .unwrap();
+        assert_eq!(link.size, "../a.txt".len() as u64);
+        assert_eq!(link.real_path, None);
+    }
+
+    #[test]
+    fn trust_no_cycles_skips_path_tracking() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.trust_no_cycles = true;
+        let (trusted, calls) = scan_counting(config, linked_tree());
+        let (checked, _) =
+            scan_counting(AnalyzerConfig::new(PathBuf::from("/data")), linked_tree());
+
+        assert_eq!(calls, 0);
+        assert_eq!(trusted.file_count, checked.file_count);
+        assert_eq!(trusted.total_size, checked.total_size);
+        assert_eq!(trusted.directory_count, checked.directory_count);
+    }
 }
@@ -1,50 +1,523 @@
 // CLI entry point
 
-use clap::Parser;
-use std::path::PathBuf;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser};
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use wiztree_metafile::{AnalyzerConfig, FileAnalyzer, TraversalStrategy};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wiztree_metafile::analyzer::relative_to;
+use wiztree_metafile::config::parse_size;
+use wiztree_metafile::manifest::{load_manifest, open_input};
+use wiztree_metafile::output::{
+    AnalyzeUrlFormatter, CaseCollisionFormatter, Compression, CsvColumn, CsvFormatter,
+    DiffFormatter, ExtensionFormatter, FlushPolicy, FlushTimer, JsonFormatter, KvFormatter,
+    LongPathFormatter, MetafileFormatter, NdjsonWriter, OutputFormatter, OutputWriter,
+    OwnerFormatter, PathListFormatter, RecentDirsFormatter, SmallFileHotspotFormatter, SortOrder,
+    StatsFormatter, TextFormatter, format_size,
+};
+use wiztree_metafile::sample::random_seed;
+use wiztree_metafile::{
+    AnalysisResult, AnalyzerConfig, FileAnalyzer, LimitBehavior, OutputFormat, PathLengthLimit,
+    Profile, ProfileOptions, RootErrorPolicy, ScanProgress, SizeMode, StdFileSystem,
+    TraversalStrategy,
+};
 
 #[derive(Parser)]
 #[command(name = "wiztree-metafile")]
 #[command(version = "0.1.0")]
 #[command(about = "Analyze directory structures and file information with esbuild metafile format output", long_about = None)]
+#[command(args_override_self = true)]
+#[command(group(
+    // Each of these selects the report written, so only one may be given
+    ArgGroup::new("mode").multiple(false).args([
+        "format",
+        "stats_only",
+        "list",
+        "print0",
+        "small_file_hotspots",
+        "case_collisions",
+        "long_paths",
+        "diff",
+        "recent_dirs",
+        "by_extension",
+        "by_owner",
+        "ndjson",
+    ])
+))]
 struct Cli {
-    /// Root directory to analyze
+    /// Root directories to analyze [default: $WIZTREE_ROOT, or none with --manifest]
     #[arg(value_name = "PATH")]
-    root: PathBuf,
+    roots: Vec<PathBuf>,
 
     /// Maximum depth to traverse (1 to system max)
     #[arg(short = 'd', long = "max-depth")]
     max_depth: Option<usize>,
 
+    /// Only read the first N entries of each directory
+    #[arg(long = "max-entries-per-dir", value_name = "N")]
+    max_entries_per_dir: Option<usize>,
+
     /// Maximum number of files to process
     #[arg(short = 'n', long = "max-files")]
     max_files: Option<usize>,
 
-    /// Traversal strategy: depth-first, breadth-first, dfs, bfs
+    /// When a limit is reached: truncate (partial result), error
+    #[arg(long = "entries-limit-behavior", default_value = "truncate")]
+    entries_limit_behavior: String,
+
+    /// Traversal strategy: depth-first, breadth-first, parallel, dfs, bfs
     #[arg(short = 's', long = "strategy", default_value = "depth-first")]
     strategy: String,
 
-    /// Minimum file size in bytes
-    #[arg(short = 'm', long = "min-size", default_value = "0")]
+    /// Minimum file size (bytes, or with a unit: 10K, 1.5M, 2GB, 500KiB)
+    #[arg(short = 'm', long = "min-size", default_value = "0", value_parser = parse_size)]
     min_size: u64,
 
-    /// Number of threads (1 to CPU count)
+    /// Maximum file size (bytes, or with a unit)
+    #[arg(long = "max-size", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Stop scanning once the total size reaches SIZE
+    #[arg(long = "max-total-size", value_name = "SIZE", value_parser = parse_size)]
+    max_total_size: Option<u64>,
+
+    /// Exit with an error when the total size exceeds SIZE
+    #[arg(long = "budget", value_name = "SIZE", value_parser = parse_size)]
+    budget: Option<u64>,
+
+    /// Number of threads (1 to CPU count, 0 for the CPU count)
     #[arg(short = 't', long = "threads")]
     threads: Option<usize>,
 
-    /// Output file path
+    /// Output file path (never counted in the scan itself)
     #[arg(short = 'o', long = "output")]
     output: Option<PathBuf>,
 
+    /// Leave DIR out of the scan, e.g. the reports/ folder outputs are written into
+    #[arg(long = "exclude-self-output-dir", value_name = "DIR")]
+    exclude_self_output_dir: Option<PathBuf>,
+
+    /// Compress the output: gzip, zstd, brotli, none [default: inferred from the output extension]
+    #[arg(long = "compress")]
+    compress: Option<String>,
+
+    /// Write one output per top-level directory, replacing {name} with its name
+    #[arg(
+        long = "output-template",
+        value_name = "TEMPLATE",
+        conflicts_with = "relativize_to"
+    )]
+    output_template: Option<String>,
+
+    /// Write collected warnings to this file as a JSON array
+    #[arg(long = "warnings-output", value_name = "FILE")]
+    warnings_output: Option<PathBuf>,
+
+    /// Exit with an error when no files are found
+    #[arg(long = "fail-on-empty")]
+    fail_on_empty: bool,
+
+    /// Print a one-line summary to stderr even when it is not a terminal
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Only output N randomly chosen entries; totals still cover the whole scan
+    #[arg(long = "sample", value_name = "N")]
+    sample: Option<usize>,
+
+    /// Seed for --sample, to reproduce an earlier run [default: random, shown with --verbose]
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+
+    /// Show a running file count, size and completion estimate on stderr
+    #[arg(long = "progress")]
+    progress: bool,
+
+    /// Record the scan's peak memory use in the results and print it to stderr
+    #[arg(long = "peak-memory")]
+    peak_memory: bool,
+
+    /// Never print the summary line to stderr (overrides --verbose)
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// When a root is invalid: abort, continue (skip it with a warning)
+    #[arg(long = "after-root-error", default_value = "abort")]
+    after_root_error: String,
+
+    /// Create missing parent directories of the output file
+    #[arg(long = "create-dirs")]
+    create_dirs: bool,
+
+    /// Output format: text, json, metafile, esbuild-analyze-url, csv, kv
+    #[arg(short = 'f', long = "format", default_value = "metafile")]
+    format: String,
+
+    /// Columns of --format csv, in order: path, size, allocated, mtime, depth,
+    /// owner, group, symlink, target, content_type
+    #[arg(long = "csv-columns", value_name = "LIST", default_value = "path,size")]
+    csv_columns: String,
+
+    /// Add a `size=N path=P` line per file to --format kv
+    #[arg(long = "kv-entries")]
+    kv_entries: bool,
+
+    /// Entry order in the output: none, path, size-desc, size-asc
+    #[arg(long = "sort-output", default_value = "none")]
+    sort_output: String,
+
     /// Ignore patterns (glob format, can be specified multiple times)
     #[arg(short = 'i', long = "ignore")]
     ignore: Vec<String>,
+
+    /// Size to report: logical, allocated [default: logical]
+    #[arg(long = "size-mode")]
+    size_mode: Option<String>,
+
+    /// Option preset applied before other flags: source (ignores VCS, dependency and build directories and .gitignore'd files), media, backup
+    #[arg(long = "profile")]
+    profile: Option<String>,
+
+    /// Skip paths matched by .gitignore files in the root and below
+    #[arg(long = "gitignore", overrides_with = "no_gitignore")]
+    gitignore: bool,
+
+    /// Count paths matched by .gitignore files, e.g. with --profile source
+    #[arg(long = "no-gitignore", overrides_with = "gitignore")]
+    no_gitignore: bool,
+
+    /// Only count files matching these patterns (glob format, can be specified multiple times)
+    #[arg(long = "include")]
+    include: Vec<String>,
+
+    /// Round sizes up to the filesystem cluster size when block info is unavailable
+    #[arg(long = "round-to-cluster")]
+    round_to_cluster: bool,
+
+    /// Only report size and depth histograms, without retaining file paths
+    #[arg(long = "stats-only")]
+    stats_only: bool,
+
+    /// Add a bar chart of file counts per depth to the text output
+    #[arg(long = "depth-histogram")]
+    depth_histogram: bool,
+
+    /// Report file count and total, mean and median size per extension
+    #[arg(long = "by-extension")]
+    by_extension: bool,
+
+    /// Report total size per owning user (Unix only)
+    #[arg(long = "by-owner")]
+    by_owner: bool,
+
+    /// Only include files owned by this uid (Unix only)
+    #[arg(long = "owner", value_name = "UID")]
+    owner: Option<u32>,
+
+    /// Only include files modified after FILE was (like find -newer)
+    #[arg(long = "newer-than", value_name = "FILE")]
+    newer_than: Option<PathBuf>,
+
+    /// Only include files modified before FILE was
+    #[arg(long = "older-than", value_name = "FILE")]
+    older_than: Option<PathBuf>,
+
+    /// Record each entry's canonical path when reached through a symlink
+    #[arg(long = "real-paths")]
+    real_paths: bool,
+
+    /// Build the result from FILE instead of scanning: `path<TAB>size` lines, or
+    /// --ndjson output when named .ndjson/.jsonl; gzipped files are decompressed
+    #[arg(long = "manifest", value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Report changes against an earlier scan saved with --format json (may be gzipped)
+    #[arg(long = "diff", value_name = "PREVIOUS")]
+    diff: Option<PathBuf>,
+
+    /// Only report resized files that changed by at least SIZE (e.g. 1M); added and removed files are always shown
+    #[arg(long = "diff-min-delta", value_name = "SIZE", default_value = "0", value_parser = parse_size)]
+    diff_min_delta: u64,
+
+    /// Report directories modified within the last SECONDS, newest first
+    #[arg(long = "recent-dirs", value_name = "SECONDS")]
+    recent_dirs: Option<u64>,
+
+    /// Count symlinks by their own size and record target sizes separately
+    #[arg(long = "symlink-target-sizes")]
+    symlink_target_sizes: bool,
+
+    /// Skip cycle tracking for speed on trees known to have no loops (unsafe otherwise)
+    #[arg(long = "trust-no-cycles")]
+    trust_no_cycles: bool,
+
+    /// Never resolve symlinks; count each at its own size
+    #[arg(long = "no-symlinks", conflicts_with_all = ["follow_symlinks_once", "symlink_target_sizes", "real_paths"])]
+    no_symlinks: bool,
+
+    /// Follow symlinks but count each target once, however many links reach it
+    #[arg(long = "follow-symlinks-once")]
+    follow_symlinks_once: bool,
+
+    /// Print one matching file path per line instead of a metafile
+    #[arg(long = "list")]
+    list: bool,
+
+    /// Like --list, but write each path unchanged and NUL-terminated (for xargs -0)
+    #[arg(long = "print0")]
+    print0: bool,
+
+    /// Stream entries as newline-delimited JSON while scanning
+    #[arg(long = "ndjson", conflicts_with_all = ["sample", "sort_output"])]
+    ndjson: bool,
+
+    /// Flush streamed output after this many entries
+    #[arg(long = "flush-entries", default_value = "1024")]
+    flush_entries: usize,
+
+    /// Flush streamed output when this many milliseconds have passed
+    #[arg(long = "flush-ms", default_value = "500")]
+    flush_ms: u64,
+
+    /// Report directories whose names differ only by case within the same parent
+    #[arg(long = "case-collisions")]
+    case_collisions: bool,
+
+    /// Report directories with at least COUNT files below --min-size
+    #[arg(long = "small-file-hotspots", value_name = "COUNT")]
+    small_file_hotspots: Option<usize>,
+
+    /// Report files whose path or name exceeds portable length limits
+    #[arg(long = "long-paths")]
+    long_paths: bool,
+
+    /// Maximum path length for --long-paths (default: platform limit)
+    #[arg(long = "max-path-length", value_name = "N")]
+    max_path_length: Option<usize>,
+
+    /// Maximum file or directory name length for --long-paths [default: 255]
+    #[arg(long = "max-name-length", value_name = "N")]
+    max_name_length: Option<usize>,
+
+    /// Tag metafile imports as import-statement (directories) or require-call (files)
+    #[arg(long = "import-kinds")]
+    import_kinds: bool,
+
+    /// Add each file's mtime and symlink flag to its metafile input's `with` map
+    #[arg(long = "file-metadata")]
+    file_metadata: bool,
+
+    /// Separator between tree levels in metafile paths (e.g. :: for manifest keys)
+    #[arg(long = "tree-separator", value_name = "SEP")]
+    tree_separator: Option<String>,
+
+    /// Add a summary input per scan root holding its total bytes
+    #[arg(long = "root-input")]
+    root_input: bool,
+
+    /// Show paths relative to this base directory when they are under it
+    #[arg(long = "relativize-to", value_name = "BASE")]
+    relativize_to: Option<PathBuf>,
+
+    /// Follow Windows shortcuts (.lnk) and record their targets
+    #[cfg(all(windows, feature = "lnk"))]
+    #[arg(long = "follow-lnk")]
+    follow_lnk: bool,
+
+    /// Include NTFS alternate data stream sizes in file sizes
+    #[cfg(all(windows, feature = "ads"))]
+    #[arg(long = "include-ads")]
+    include_ads: bool,
+
+    /// Detect each file's MIME type from its leading bytes
+    #[cfg(feature = "content-type")]
+    #[arg(long = "sniff-content-type")]
+    sniff_content_type: bool,
+
+    /// Only include files whose detected MIME type matches (e.g. image/png, image/*)
+    #[cfg(feature = "content-type")]
+    #[arg(long = "content-type", value_name = "MIME")]
+    content_type: Option<String>,
+}
+
+/// Thread count for `--threads`; 0 means auto-detect, the same as omitting
+/// the flag
+fn resolve_threads(threads: usize) -> usize {
+    if threads == 0 {
+        num_cpus::get()
+    } else {
+        threads
+    }
+}
+
+/// Process exit code for a finished scan: 1 when `--fail-on-empty` is set
+/// and no files were found, 0 otherwise
+fn exit_code(cli: &Cli, result: &AnalysisResult) -> i32 {
+    if cli.fail_on_empty && result.file_count == 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Environment variable holding the default root when no PATH is given
+const ROOT_ENV: &str = "WIZTREE_ROOT";
+
+/// Environment variable holding default flags, overridden by command-line flags
+const OPTS_ENV: &str = "WIZTREE_OPTS";
+
+/// Get the command-line arguments with the `WIZTREE_OPTS` defaults inserted
+/// before them, so explicit flags take precedence
+fn args_with_env_defaults() -> Vec<OsString> {
+    with_defaults(env::args_os().collect(), env::var_os(OPTS_ENV))
+}
+
+/// Insert whitespace-separated default flags after the program name
+fn with_defaults(mut args: Vec<OsString>, defaults: Option<OsString>) -> Vec<OsString> {
+    if let Some(defaults) = defaults {
+        let defaults = defaults
+            .to_string_lossy()
+            .split_whitespace()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+        let at = args.len().min(1);
+        args.splice(at..at, defaults);
+    }
+    args
+}
+
+/// Set the options a profile presets, letting explicit flags win
+///
+/// `--size-mode`, `--ignore` and `--include` replace the profile's settings,
+/// and `--gitignore` or `--no-gitignore` its gitignore handling. Returns the
+/// message to print on invalid options.
+fn apply_profile(
+    cli: &Cli,
+    profile: ProfileOptions,
+    config: &mut AnalyzerConfig,
+) -> Result<(), String> {
+    let size_mode = cli
+        .size_mode
+        .as_deref()
+        .map(str::parse::<SizeMode>)
+        .transpose()
+        .map_err(|e| format!("Error: {}", e))?;
+    config.size_mode = size_mode.or(profile.size_mode).unwrap_or_default();
+    config.respect_gitignore = if cli.gitignore || cli.no_gitignore {
+        cli.gitignore
+    } else {
+        profile.respect_gitignore
+    };
+
+    let ignore = if cli.ignore.is_empty() {
+        profile.ignore_patterns
+    } else {
+        cli.ignore.clone()
+    };
+    if !ignore.is_empty() {
+        config
+            .set_ignore_patterns(ignore)
+            .map_err(|e| format!("Error setting ignore patterns: {}", e))?;
+    }
+
+    let include = if cli.include.is_empty() {
+        profile.include_patterns
+    } else {
+        cli.include.clone()
+    };
+    if !include.is_empty() {
+        config
+            .set_include_patterns(include)
+            .map_err(|e| format!("Error setting include patterns: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Width of the longest depth chart bar: fit a terminal's width, as given by
+/// `COLUMNS`, or a fixed width when not writing to a terminal
+fn depth_chart_width() -> usize {
+    const FIXED_WIDTH: usize = 40;
+    // Room for the depth label and the count around each bar
+    const MARGIN: usize = 20;
+
+    if !io::stdout().is_terminal() {
+        return FIXED_WIDTH;
+    }
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .map_or(FIXED_WIDTH, |columns| {
+            columns.saturating_sub(MARGIN).max(10)
+        })
+}
+
+/// Format a count with thousands separators
+fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// One-line summary of a finished scan, e.g. "Scanned 1,234 files, 5.6 MB in 1.2s"
+fn exit_summary(result: &AnalysisResult, elapsed: Duration) -> String {
+    format!(
+        "Scanned {} files, {} in {:.1}s",
+        group_digits(result.file_count),
+        format_size(result.total_size),
+        elapsed.as_secs_f64()
+    )
+}
+
+/// Print the warnings of a result that was loaded rather than scanned
+fn print_warnings(result: &AnalysisResult) {
+    for warning in &result.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// Build one result from the member lists of tar archive roots
+#[cfg(feature = "tar")]
+fn load_tar_roots(roots: &[PathBuf]) -> Result<AnalysisResult, wiztree_metafile::AnalyzerError> {
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+    let mut warning_details = Vec::new();
+    for root in roots {
+        let loaded = wiztree_metafile::archive::load_tar(root)?;
+        entries.extend(loaded.entries);
+        warnings.extend(loaded.warnings);
+        warning_details.extend(loaded.warning_details);
+    }
+    let mut result = AnalysisResult::from_entries(entries);
+    result.warnings = warnings;
+    result.warning_details = warning_details;
+    Ok(result)
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse_from(args_with_env_defaults());
+
+    // Fall back to the root from the environment; a manifest needs no root
+    if cli.roots.is_empty() && cli.manifest.is_none() {
+        match env::var_os(ROOT_ENV) {
+            Some(root) if !root.is_empty() => cli.roots.push(PathBuf::from(root)),
+            _ => Cli::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    format!("no PATH given and {} is not set", ROOT_ENV),
+                )
+                .exit(),
+        }
+    }
 
     // Parse traversal strategy
     let strategy = match cli.strategy.parse::<TraversalStrategy>() {
@@ -55,43 +528,637 @@ fn main() {
         }
     };
 
+    // Parse output format
+    let format = match cli.format.parse::<OutputFormat>() {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let csv_columns = match CsvColumn::parse_list(&cli.csv_columns) {
+        Ok(columns) => columns,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let csv_selected = |column| format == OutputFormat::Csv && csv_columns.contains(&column);
+
+    let sort_order = match cli.sort_output.parse::<SortOrder>() {
+        Ok(order) => order,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // Parse output compression, inferring it from the output file extension
+    let compression = match cli.compress.as_deref().map(str::parse::<Compression>) {
+        Some(Ok(c)) => c,
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        None => cli
+            .output
+            .as_deref()
+            .or(cli.output_template.as_deref().map(Path::new))
+            .map(Compression::from_extension)
+            .unwrap_or_default(),
+    };
+    if let Some(ref template) = cli.output_template
+        && (!template.contains("{name}") || cli.output.is_some() || cli.ndjson)
+    {
+        eprintln!(
+            "Error: --output-template must contain {{name}} and cannot be combined with --output or --ndjson"
+        );
+        process::exit(1);
+    }
+    if cli.ndjson && cli.manifest.is_some() {
+        eprintln!("Error: --ndjson streams a scan and cannot be combined with --manifest");
+        process::exit(1);
+    }
+    if (cli.newer_than.is_some() || cli.older_than.is_some()) && cli.manifest.is_some() {
+        eprintln!(
+            "Error: --newer-than and --older-than compare scanned mtimes and cannot be combined with --manifest"
+        );
+        process::exit(1);
+    }
+    // Roots that are tar archives are read from their headers instead of scanned
+    #[cfg(feature = "tar")]
+    let tar_roots = {
+        let archives = cli
+            .roots
+            .iter()
+            .filter(|root| wiztree_metafile::archive::is_tar_archive(root) && root.is_file())
+            .count();
+        if archives > 0 && (archives < cli.roots.len() || cli.manifest.is_some() || cli.ndjson) {
+            eprintln!(
+                "Error: tar archive roots cannot be combined with directory roots, --manifest or --ndjson"
+            );
+            process::exit(1);
+        }
+        archives > 0
+    };
+    if cli.small_file_hotspots.is_some() && cli.min_size == 0 {
+        eprintln!("Error: --small-file-hotspots counts files below --min-size, which must be set");
+        process::exit(1);
+    }
+    if cli.ndjson && compression != Compression::None {
+        eprintln!("Error: compressed output is not supported with --ndjson");
+        process::exit(1);
+    }
+
+    // Parse profile; its options apply first and explicit flags override them
+    let profile = match cli.profile.as_deref().map(str::parse::<Profile>) {
+        Some(Ok(p)) => p.options(),
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        None => ProfileOptions::default(),
+    };
+
+    // Resolve the display base against the current directory
+    let relativize_to = match cli.relativize_to.as_deref().map(std::path::absolute) {
+        Some(Ok(base)) => Some(base),
+        Some(Err(e)) => {
+            eprintln!("Error resolving --relativize-to: {}", e);
+            process::exit(1);
+        }
+        None => None,
+    };
+
+    // Load the earlier scan to compare against
+    let previous = cli.diff.as_deref().map(|path| {
+        let loaded = open_input(path)
+            .map_err(|e| e.to_string())
+            .and_then(|reader| {
+                serde_json::from_reader::<_, AnalysisResult>(reader).map_err(|e| e.to_string())
+            });
+        match loaded {
+            Ok(mut previous) => {
+                if let Some(ref base) = relativize_to {
+                    previous.relativize(base);
+                }
+                previous
+            }
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    });
+
+    // Parse limit behavior
+    let limit_behavior = match cli.entries_limit_behavior.parse::<LimitBehavior>() {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // Parse root error policy
+    let root_error_policy = match cli.after_root_error.parse::<RootErrorPolicy>() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
     // Build configuration
-    let mut config = AnalyzerConfig::new(cli.root);
+    let roots = cli.roots.clone();
+    let mut config = AnalyzerConfig::new(roots.first().cloned().unwrap_or_else(|| ".".into()));
+    config.additional_roots = roots.iter().skip(1).cloned().collect();
+    config.root_error_policy = root_error_policy;
     config.max_depth = cli.max_depth;
     config.max_files = cli.max_files;
+    config.max_entries_per_dir = cli.max_entries_per_dir;
+    config.limit_behavior = limit_behavior;
     config.traversal_strategy = strategy;
     config.min_file_size = cli.min_size;
+    config.max_file_size = cli.max_size;
+    config.max_total_size = cli.max_total_size;
     config.output_path = cli.output.clone();
+    config.exclude_output_dir = cli.exclude_self_output_dir.clone();
+    config.stats_only = cli.stats_only;
+    config.round_to_cluster = cli.round_to_cluster;
+    config.capture_owner =
+        cli.by_owner || csv_selected(CsvColumn::Owner) || csv_selected(CsvColumn::Group);
+    config.owner = cli.owner;
+    config.symlink_target_sizes = cli.symlink_target_sizes;
+    config.follow_symlinks_once = cli.follow_symlinks_once;
+    config.no_symlinks = cli.no_symlinks;
+    config.trust_no_cycles = cli.trust_no_cycles;
+    // Sampling is the only randomized output, so only sampled runs draw a seed
+    let seed = cli.sample.map(|_| cli.seed.unwrap_or_else(random_seed));
+    config.seed = seed;
+    config.record_dir_mtimes = cli.recent_dirs.is_some();
+    config.record_directories = cli.output_template.is_some();
+    config.record_real_path = cli.real_paths;
+    config.track_peak_memory = cli.peak_memory;
+    config.count_small_files = cli.small_file_hotspots.is_some();
+    config.record_file_mtimes = cli.file_metadata || csv_selected(CsvColumn::Mtime);
+    #[cfg(all(windows, feature = "lnk"))]
+    {
+        config.follow_lnk = cli.follow_lnk;
+    }
+    #[cfg(all(windows, feature = "ads"))]
+    {
+        config.include_ads = cli.include_ads;
+    }
+    #[cfg(feature = "content-type")]
+    {
+        config.sniff_content_type = cli.sniff_content_type;
+        config.content_type_filter = cli.content_type.clone();
+    }
 
-    // Set ignore patterns
-    if !cli.ignore.is_empty()
-        && let Err(e) = config.set_ignore_patterns(cli.ignore)
+    // Resolve the reference files' mtimes into the modification time bounds
+    if let Some(ref reference) = cli.newer_than
+        && let Err(e) = config.set_newer_than(reference, &StdFileSystem)
+    {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    if let Some(ref reference) = cli.older_than
+        && let Err(e) = config.set_older_than(reference, &StdFileSystem)
     {
-        eprintln!("Error setting ignore patterns: {}", e);
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+
+    // Set the size mode, gitignore handling and patterns
+    if let Err(e) = apply_profile(&cli, profile, &mut config) {
+        eprintln!("{}", e);
         process::exit(1);
     }
 
     // Set thread count
     if let Some(threads) = cli.threads {
-        config.thread_count = threads;
+        config.thread_count = resolve_threads(threads);
         config.clamp_thread_count();
     }
 
     // Run analysis
-    let analyzer = FileAnalyzer::new(config);
-    match analyzer.analyze() {
-        Ok(result) => {
-            // Write output
-            if let Err(e) =
-                wiztree_metafile::output::OutputWriter::write(&result, cli.output.as_deref())
-            {
-                eprintln!("Error writing output: {}", e);
-                process::exit(1);
+    let mut analyzer = FileAnalyzer::new(config);
+
+    // Stream entries as NDJSON while scanning
+    let stream = if cli.ndjson {
+        let destination: Box<dyn Write + Send> = match cli.output.as_deref() {
+            Some(path) => match OutputWriter::create_file(path, cli.create_dirs) {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    eprintln!("Error writing output: {}", e);
+                    process::exit(1);
+                }
+            },
+            None => Box::new(io::stdout()),
+        };
+        let policy = FlushPolicy {
+            max_entries: cli.flush_entries.max(1),
+            max_interval: Duration::from_millis(cli.flush_ms),
+        };
+        let writer = Arc::new(Mutex::new(NdjsonWriter::new(destination, policy)));
+        let sink = writer.clone();
+        let base = relativize_to.clone();
+        analyzer.set_entry_sink(Arc::new(move |entry| match base {
+            Some(ref base) => {
+                let mut entry = entry.clone();
+                entry.relativize(base);
+                sink.lock().unwrap().write_entry(&entry);
             }
-        }
+            None => sink.lock().unwrap().write_entry(entry),
+        }));
+        // Entries go straight to the output, so don't hold them in memory too
+        analyzer.set_retain_entries(false);
+        let timer = FlushTimer::start(writer.clone());
+        Some((writer, timer))
+    } else {
+        None
+    };
+
+    if cli.progress {
+        // Redraw at most every 100ms so printing doesn't slow the scan
+        let last_drawn = Mutex::new(None::<Instant>);
+        analyzer.set_progress_callback(Arc::new(move |progress: &ScanProgress| {
+            let mut last_drawn = last_drawn.lock().unwrap();
+            if last_drawn.is_some_and(|t| t.elapsed() < Duration::from_millis(100)) {
+                return;
+            }
+            *last_drawn = Some(Instant::now());
+
+            let estimate = progress
+                .estimated_fraction
+                .map(|f| format!(" (~{:.0}%)", f * 100.0))
+                .unwrap_or_default();
+            eprint!(
+                "\rScanned {} files, {}{}\x1b[K",
+                group_digits(progress.files),
+                format_size(progress.bytes),
+                estimate
+            );
+        }));
+    }
+
+    let started = Instant::now();
+    let analyzed = match cli.manifest {
+        Some(ref manifest) => load_manifest(manifest)
+            .map(|loaded| loaded.filter_entries(analyzer.config()))
+            .inspect(print_warnings),
+        #[cfg(feature = "tar")]
+        None if tar_roots => load_tar_roots(&roots)
+            .map(|loaded| loaded.filter_entries(analyzer.config()))
+            .inspect(print_warnings),
+        None => analyzer.analyze(),
+    };
+    if cli.progress {
+        // Clear the progress line
+        eprint!("\r\x1b[K");
+    }
+    let mut result = match analyzed {
+        Ok(result) => result,
         Err(e) => {
             eprintln!("Error: {}", e);
             process::exit(1);
         }
+    };
+
+    // Every output shows the same paths, so rewrite them once up front
+    if let Some(ref base) = relativize_to {
+        result.relativize(base);
+    }
+
+    if let Some((stream, timer)) = stream {
+        // Entries were already written while scanning
+        drop(analyzer);
+        drop(timer);
+        let writer = Arc::into_inner(stream).map(|w| w.into_inner().unwrap());
+        if let Some(Err(e)) = writer.map(NdjsonWriter::finish) {
+            eprintln!("Error writing output: {}", e);
+            process::exit(1);
+        }
+    } else {
+        if let (Some(count), Some(seed)) = (cli.sample, seed) {
+            result.entries = result.sample(count, seed).into_iter().cloned().collect();
+        }
+        sort_order.sort(&mut result.entries);
+
+        // Select output formatter
+        let formatter: Box<dyn OutputFormatter> = if cli.stats_only {
+            Box::new(StatsFormatter)
+        } else if cli.list || cli.print0 {
+            Box::new(if cli.print0 {
+                PathListFormatter::nul()
+            } else {
+                PathListFormatter::lines()
+            })
+        } else if let Some(threshold_count) = cli.small_file_hotspots {
+            Box::new(SmallFileHotspotFormatter { threshold_count })
+        } else if cli.case_collisions {
+            Box::new(CaseCollisionFormatter)
+        } else if cli.long_paths {
+            let mut limit = PathLengthLimit::default();
+            if let Some(max_path) = cli.max_path_length {
+                limit.max_path = max_path;
+            }
+            if let Some(max_component) = cli.max_name_length {
+                limit.max_component = max_component;
+            }
+            Box::new(LongPathFormatter { limit })
+        } else if let Some(previous) = previous {
+            Box::new(DiffFormatter {
+                previous,
+                min_delta: cli.diff_min_delta,
+            })
+        } else if let Some(seconds) = cli.recent_dirs {
+            Box::new(RecentDirsFormatter::new(Duration::from_secs(seconds)))
+        } else if cli.by_extension {
+            Box::new(ExtensionFormatter)
+        } else if cli.by_owner {
+            Box::new(OwnerFormatter)
+        } else {
+            let metafile = MetafileFormatter {
+                import_kinds: cli.import_kinds,
+                root_inputs: if cli.root_input {
+                    roots
+                        .iter()
+                        .map(|root| match relativize_to {
+                            Some(ref base) => relative_to(root, base),
+                            None => root.clone(),
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                file_metadata: cli.file_metadata,
+                separator: cli.tree_separator.clone(),
+            };
+            match format {
+                OutputFormat::Text => Box::new(TextFormatter {
+                    depth_chart_width: cli.depth_histogram.then(depth_chart_width),
+                }),
+                OutputFormat::Json => Box::new(JsonFormatter),
+                OutputFormat::Kv => Box::new(KvFormatter {
+                    entries: cli.kv_entries,
+                }),
+                OutputFormat::Csv => Box::new(CsvFormatter {
+                    columns: csv_columns,
+                }),
+                OutputFormat::Metafile => Box::new(metafile),
+                OutputFormat::EsbuildAnalyzeUrl => Box::new(AnalyzeUrlFormatter {
+                    metafile,
+                    ..Default::default()
+                }),
+            }
+        };
+
+        // Write output, one file per top-level directory with a template
+        let written = match cli.output_template {
+            Some(ref template) => {
+                result
+                    .split_by_top_level_dir(&roots)
+                    .iter()
+                    .try_for_each(|(name, group)| {
+                        let path = PathBuf::from(template.replace("{name}", name));
+                        OutputWriter::write_with(
+                            formatter.as_ref(),
+                            group,
+                            Some(&path),
+                            cli.create_dirs,
+                            compression,
+                        )
+                    })
+            }
+            None => OutputWriter::write_with(
+                formatter.as_ref(),
+                &result,
+                cli.output.as_deref(),
+                cli.create_dirs,
+                compression,
+            ),
+        };
+        if let Err(e) = written {
+            eprintln!("Error writing output: {}", e);
+            process::exit(1);
+        }
+    }
+
+    if let Some(ref path) = cli.warnings_output
+        && let Err(e) = OutputWriter::write_warnings(&result, path, cli.create_dirs)
+    {
+        eprintln!("Error writing warnings: {}", e);
+        process::exit(1);
+    }
+
+    // Fail when the scan is over budget, for use as a CI check
+    if let Some(budget) = cli.budget
+        && result.total_size > budget
+    {
+        eprintln!(
+            "Error: total size {} bytes exceeds budget of {} bytes",
+            result.total_size, budget
+        );
+        process::exit(1);
+    }
+
+    // Fail when nothing was found, so automation can tell it from success
+    let code = exit_code(&cli, &result);
+    if code != 0 {
+        eprintln!("Error: no files found");
+        process::exit(code);
+    }
+
+    // Give interactive runs feedback even when the output went to a file
+    if !cli.quiet && (cli.verbose || io::stderr().is_terminal()) {
+        eprintln!("{}", exit_summary(&result, started.elapsed()));
+    }
+    if let Some(seed) = seed
+        && cli.verbose
+        && !cli.quiet
+    {
+        eprintln!("Seed: {}", seed);
+    }
+    if let Some(peak) = result.metadata.peak_memory
+        && !cli.quiet
+    {
+        eprintln!("Peak Memory: {}", format_size(peak));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiztree_metafile::MemoryFileSystem;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("wiztree-metafile").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn empty_scans_fail_only_when_asked() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_dir("/empty").add_file("/data/a.txt", 10);
+        let scan = |root: &str| {
+            let config = AnalyzerConfig::new(PathBuf::from(root));
+            FileAnalyzer::with_file_system(config, Arc::new(fs.clone()))
+                .analyze()
+                .unwrap()
+        };
+        let (empty, found) = (scan("/empty"), scan("/data"));
+
+        let cli = parse(&[".", "--fail-on-empty"]).unwrap();
+        assert_eq!(exit_code(&cli, &empty), 1);
+        assert_eq!(exit_code(&cli, &found), 0);
+        let cli = parse(&["."]).unwrap();
+        assert_eq!(exit_code(&cli, &empty), 0);
+    }
+
+    #[test]
+    fn report_modes_are_mutually_exclusive() {
+        assert!(parse(&[".", "--format", "esbuild-analyze-url"]).is_ok());
+        assert!(parse(&[".", "--ndjson", "--flush-entries", "1"]).is_ok());
+        for args in [
+            &[".", "--format", "json", "--list"][..],
+            &[".", "--stats-only", "--by-owner"],
+            &[".", "--stats-only", "--by-extension"],
+            &[".", "--print0", "--long-paths"],
+            &[".", "--print0", "--case-collisions"],
+            &[".", "--ndjson", "--stats-only"],
+            &[".", "--ndjson", "--format", "json"],
+            &[".", "--ndjson", "--by-extension"],
+            // Streamed entries are written as found, so they can't be
+            // sampled or sorted
+            &[".", "--ndjson", "--sample", "10"],
+            &[".", "--ndjson", "--sort-output", "size-desc"],
+        ] {
+            let error = parse(args).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::ArgumentConflict, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn env_defaults_are_overridden_by_flags() {
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        let merged = with_defaults(
+            args(&["wiztree-metafile", "--max-depth", "5", "/data"]),
+            Some(OsString::from(" --max-depth 2  --fail-on-empty ")),
+        );
+        assert_eq!(
+            merged,
+            args(&[
+                "wiztree-metafile",
+                "--max-depth",
+                "2",
+                "--fail-on-empty",
+                "--max-depth",
+                "5",
+                "/data"
+            ])
+        );
+
+        let cli = Cli::try_parse_from(merged).unwrap();
+        assert_eq!(cli.max_depth, Some(5));
+        assert!(cli.fail_on_empty);
+        assert_eq!(cli.roots, [PathBuf::from("/data")]);
+
+        assert_eq!(with_defaults(args(&["x"]), None), args(&["x"]));
+    }
+
+    #[test]
+    fn exit_summary_groups_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1_000), "1,000");
+        assert_eq!(group_digits(1_234_567), "1,234,567");
+
+        let mut result = AnalysisResult::from_entries(Vec::new());
+        result.file_count = 1_234_567;
+        result.total_size = 2048;
+        assert_eq!(
+            exit_summary(&result, Duration::from_millis(12_345)),
+            format!("Scanned 1,234,567 files, {} in 12.3s", format_size(2048))
+        );
+    }
+
+    #[test]
+    fn explicit_flags_override_the_profile() {
+        let configure = |args: &[&str]| {
+            let cli = parse(args).unwrap();
+            let profile = cli.profile.as_deref().unwrap().parse::<Profile>().unwrap();
+            let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+            apply_profile(&cli, profile.options(), &mut config).map(|()| config)
+        };
+
+        let preset = configure(&["--profile", "backup"]).unwrap();
+        assert_eq!(preset.size_mode, SizeMode::Allocated);
+        assert!(preset.should_ignore(Path::new("/data/.cache/x")));
+
+        let config = configure(&[
+            "--profile",
+            "backup",
+            "--size-mode",
+            "logical",
+            "--ignore",
+            "**/*.bak",
+        ])
+        .unwrap();
+        assert_eq!(config.size_mode, SizeMode::Logical);
+        assert!(!config.should_ignore(Path::new("/data/.cache/x")));
+        assert!(config.should_ignore(Path::new("/data/a.bak")));
+
+        // Build output can be counted by replacing the profile's patterns
+        let source = configure(&["--profile", "source"]).unwrap();
+        assert!(source.should_ignore(Path::new("/data/target/x")));
+        let config = configure(&["--profile", "source", "--ignore", "**/.git/**"]).unwrap();
+        assert!(!config.should_ignore(Path::new("/data/target/x")));
+        assert!(config.should_ignore(Path::new("/data/.git/HEAD")));
+
+        let media = configure(&["--profile", "media"]).unwrap();
+        assert!(media.should_include_file(Path::new("/data/a.jpg")));
+        assert!(!media.should_include_file(Path::new("/data/b.rs")));
+        let config = configure(&["--profile", "media", "--include", "**/*.rs"]).unwrap();
+        assert!(!config.should_include_file(Path::new("/data/a.jpg")));
+        assert!(config.should_include_file(Path::new("/data/b.rs")));
+
+        assert!(
+            configure(&["--profile", "source"])
+                .unwrap()
+                .respect_gitignore
+        );
+        assert!(
+            !configure(&["--profile", "media"])
+                .unwrap()
+                .respect_gitignore
+        );
+        assert!(
+            configure(&["--profile", "media", "--gitignore"])
+                .unwrap()
+                .respect_gitignore
+        );
+        assert!(
+            !configure(&["--profile", "source", "--no-gitignore"])
+                .unwrap()
+                .respect_gitignore
+        );
+        // The last of --gitignore and --no-gitignore wins
+        assert!(
+            configure(&["--profile", "source", "--no-gitignore", "--gitignore"])
+                .unwrap()
+                .respect_gitignore
+        );
+        assert_eq!(
+            configure(&["--profile", "media", "--size-mode", "huge"]).err(),
+            Some("Error: Invalid size mode: huge".to_string())
+        );
+    }
+
+    #[test]
+    fn zero_threads_means_one_per_cpu() {
+        assert_eq!(resolve_threads(0), num_cpus::get());
+        assert_eq!(resolve_threads(3), 3);
     }
 }
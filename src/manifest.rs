@@ -0,0 +1,258 @@
+// Results built from a path and size listing instead of a scan
+
+use crate::analyzer::{AnalysisResult, FileEntry, WarningKind};
+use crate::error::AnalyzerError;
+use flate2::bufread::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Component, Path, PathBuf};
+
+/// Leading bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Read a manifest of `path<TAB>size` lines into a result
+///
+/// Sizes are bytes and are trusted as given; nothing is stat'ed, so the
+/// paths may come from an inventory of storage that can't be read directly.
+/// Blank lines are skipped. Malformed lines are skipped with a warning in
+/// the result. An entry's depth is its number of normal path components.
+pub fn read_manifest<R: BufRead>(reader: R) -> Result<AnalysisResult, AnalyzerError> {
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Split at the last tab so paths may contain tabs
+        let parsed = line
+            .rsplit_once('\t')
+            .filter(|(path, _)| !path.is_empty())
+            .and_then(|(path, size)| Some((path, size.trim().parse::<u64>().ok()?)));
+        let Some((path, size)) = parsed else {
+            warnings.push(format!(
+                "Malformed manifest line {} (expected path<TAB>size): {}",
+                index + 1,
+                line
+            ));
+            continue;
+        };
+
+        let path = PathBuf::from(path);
+        let depth = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .count();
+        entries.push(FileEntry {
+            path,
+            size,
+            depth,
+            ..Default::default()
+        });
+    }
+
+    let mut result = AnalysisResult::from_entries(entries);
+    for warning in warnings {
+        result.add_warning(WarningKind::MalformedInput, None, warning);
+    }
+    Ok(result)
+}
+
+/// Read entries written by `--ndjson`, one JSON object per line
+///
+/// Blank lines are skipped. Lines that don't parse as an entry are skipped
+/// with a warning in the result.
+pub fn read_ndjson<R: BufRead>(reader: R) -> Result<AnalysisResult, AnalyzerError> {
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<FileEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warnings.push(format!("Malformed NDJSON line {}: {}", index + 1, e)),
+        }
+    }
+
+    let mut result = AnalysisResult::from_entries(entries);
+    for warning in warnings {
+        result.add_warning(WarningKind::MalformedInput, None, warning);
+    }
+    Ok(result)
+}
+
+/// Open an input file, transparently decompressing gzip
+///
+/// Compression is detected from the gzip magic bytes, so it works whatever
+/// the file is named.
+pub fn open_input(path: &Path) -> Result<Box<dyn BufRead>, AnalyzerError> {
+    let file = File::open(path).map_err(|e| {
+        AnalyzerError::PathError(format!("Failed to open {}: {}", path.display(), e))
+    })?;
+    let mut reader = BufReader::new(file);
+    let head = reader.fill_buf().map_err(|e| {
+        AnalyzerError::PathError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    if head.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Read a manifest file, possibly gzipped
+///
+/// Files named `.ndjson` or `.jsonl` (before any `.gz`) are read with
+/// [`read_ndjson`], others with [`read_manifest`].
+pub fn load_manifest(path: &Path) -> Result<AnalysisResult, AnalyzerError> {
+    let reader = open_input(path)?;
+    if is_ndjson(path) {
+        read_ndjson(reader)
+    } else {
+        read_manifest(reader)
+    }
+}
+
+/// Check if a file name has an NDJSON extension, ignoring a trailing `.gz`
+fn is_ndjson(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    name.ends_with(".ndjson") || name.ends_with(".jsonl")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnalyzerConfig;
+
+    const MANIFEST: &str = "/data/a.txt\t10\n\
+        /data/src/b\tc.rs\t 20\n\
+        \n\
+        /data/node_modules/x.js\t40\r\n\
+        /data/bad\tten\n\
+        no-size-here\n";
+
+    #[test]
+    fn manifest_sizes_are_taken_as_given() {
+        let result = read_manifest(MANIFEST.as_bytes()).unwrap();
+
+        let entries: Vec<(&Path, u64, usize)> = result
+            .entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.size, e.depth))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (Path::new("/data/a.txt"), 10, 2),
+                (Path::new("/data/src/b\tc.rs"), 20, 3),
+                (Path::new("/data/node_modules/x.js"), 40, 3),
+            ]
+        );
+        assert_eq!(result.total_size, 70);
+        assert_eq!(result.warnings.len(), 2);
+        assert!(result.warnings[0].starts_with("Malformed manifest line 5"));
+        assert!(
+            result
+                .warning_details
+                .iter()
+                .all(|d| d.kind == WarningKind::MalformedInput)
+        );
+    }
+
+    #[test]
+    fn scan_filters_apply_to_loaded_manifests() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config
+            .set_ignore_patterns(vec!["**/node_modules/**".to_string()])
+            .unwrap();
+        config.min_file_size = 15;
+
+        let result = read_manifest(MANIFEST.as_bytes())
+            .unwrap()
+            .filter_entries(&config);
+        assert_eq!(result.total_size, 20);
+        assert_eq!(result.warnings.len(), 2);
+    }
+
+    #[test]
+    fn unreadable_inputs_name_the_path() {
+        let missing = Path::new("/nonexistent/manifest.txt");
+        let error = open_input(missing).err().unwrap();
+        assert!(
+            matches!(error, AnalyzerError::PathError(ref message)
+                if message.starts_with("Failed to open /nonexistent/manifest.txt")),
+            "{}",
+            error
+        );
+
+        // A directory opens, but reading it fails
+        #[cfg(unix)]
+        {
+            let dir = std::env::temp_dir();
+            let error = open_input(&dir).err().unwrap();
+            let expected = format!("Failed to read {}", dir.display());
+            assert!(
+                matches!(error, AnalyzerError::PathError(ref message)
+                    if message.starts_with(&expected)),
+                "{}",
+                error
+            );
+        }
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn sizes(result: &AnalysisResult) -> Vec<(PathBuf, u64)> {
+        result
+            .entries
+            .iter()
+            .map(|e| (e.path.clone(), e.size))
+            .collect()
+    }
+
+    #[test]
+    fn gzipped_inputs_load_like_plain_ones() {
+        let dir = std::env::temp_dir().join(format!("wiztree-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ndjson: String = read_manifest(MANIFEST.as_bytes())
+            .unwrap()
+            .entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect();
+        std::fs::write(dir.join("scan.ndjson"), &ndjson).unwrap();
+        std::fs::write(dir.join("scan.ndjson.gz"), gzip(ndjson.as_bytes())).unwrap();
+        // Detected by the magic bytes, not the name
+        std::fs::write(dir.join("manifest.txt"), gzip(MANIFEST.as_bytes())).unwrap();
+
+        let plain = load_manifest(&dir.join("scan.ndjson"));
+        let gzipped = load_manifest(&dir.join("scan.ndjson.gz"));
+        let manifest = load_manifest(&dir.join("manifest.txt"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let plain = plain.unwrap();
+        assert_eq!(plain.file_count, 3);
+        assert!(plain.warnings.is_empty());
+        assert_eq!(sizes(&gzipped.unwrap()), sizes(&plain));
+        let manifest = manifest.unwrap();
+        assert_eq!(sizes(&manifest), sizes(&plain));
+        assert_eq!(manifest.warnings.len(), 2);
+    }
+}
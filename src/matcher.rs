@@ -0,0 +1,210 @@
+// Glob matching with fast paths for common pattern shapes
+
+use crate::error::AnalyzerError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Matches paths against a set of glob patterns
+///
+/// Patterns of the form `**/NAME/**`, where `NAME` is a plain directory name,
+/// are answered by a component lookup, and extension patterns (`*.EXT` or
+/// `**/*.EXT`) by a suffix comparison, instead of glob evaluation. All other
+/// patterns are compiled into a single `GlobSet`. Match decisions are the
+/// same as matching every pattern with `globset`.
+#[derive(Debug, Clone, Default)]
+pub struct PatternMatcher {
+    /// Names of directories whose contents match anywhere in the tree
+    dir_names: Vec<Vec<u8>>,
+    /// Path endings, including the dot, of extension patterns
+    suffixes: Vec<Vec<u8>>,
+    globs: Option<GlobSet>,
+}
+
+impl PatternMatcher {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, AnalyzerError> {
+        let mut dir_names = Vec::new();
+        let mut suffixes = Vec::new();
+        let mut builder = GlobSetBuilder::new();
+        let mut glob_count = 0;
+
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            let glob = Glob::new(pattern).map_err(|e| {
+                AnalyzerError::InvalidConfig(format!("Invalid glob pattern '{}': {}", pattern, e))
+            })?;
+
+            if let Some(name) = Self::dir_name(pattern) {
+                if !dir_names.iter().any(|n: &Vec<u8>| n == name.as_bytes()) {
+                    dir_names.push(name.as_bytes().to_vec());
+                }
+            } else if let Some(suffix) = Self::extension_suffix(pattern) {
+                if !suffixes.iter().any(|s: &Vec<u8>| s == suffix.as_bytes()) {
+                    suffixes.push(suffix.as_bytes().to_vec());
+                }
+            } else {
+                builder.add(glob);
+                glob_count += 1;
+            }
+        }
+
+        let globs = if glob_count > 0 {
+            Some(builder.build().map_err(|e| {
+                AnalyzerError::InvalidConfig(format!("Failed to build glob set: {}", e))
+            })?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            dir_names,
+            suffixes,
+            globs,
+        })
+    }
+
+    /// Check if a path matches any pattern
+    pub fn is_match(&self, path: &Path) -> bool {
+        if !self.suffixes.is_empty() && self.has_suffix(path) {
+            return true;
+        }
+        if !self.dir_names.is_empty() && self.in_named_dir(path) {
+            return true;
+        }
+        self.globs
+            .as_ref()
+            .is_some_and(|globs| globs.is_match(path))
+    }
+
+    /// Check if any component other than the last is one of the directory names
+    ///
+    /// `**/NAME/**` needs something after `NAME`, so a trailing separator
+    /// counts as a following component.
+    fn in_named_dir(&self, path: &Path) -> bool {
+        let bytes = path.as_os_str().as_encoded_bytes();
+        let mut components = bytes.split(|b| *b == b'/' || (cfg!(windows) && *b == b'\\'));
+        let Some(mut current) = components.next() else {
+            return false;
+        };
+        for next in components {
+            if self.dir_names.iter().any(|name| name.as_slice() == current) {
+                return true;
+            }
+            current = next;
+        }
+        false
+    }
+
+    /// Check if the path ends with one of the extension suffixes
+    ///
+    /// `*` crosses separators in `globset`'s default mode, so `*.EXT`
+    /// matches any path ending in `.EXT`.
+    fn has_suffix(&self, path: &Path) -> bool {
+        let bytes = path.as_os_str().as_encoded_bytes();
+        self.suffixes.iter().any(|suffix| bytes.ends_with(suffix))
+    }
+
+    /// Extract `.EXT` from a `*.EXT` or `**/*.EXT` pattern with a plain extension
+    fn extension_suffix(pattern: &str) -> Option<&str> {
+        let suffix = pattern.strip_prefix("**/").unwrap_or(pattern);
+        let suffix = suffix.strip_prefix('*')?;
+        let plain = suffix.len() > 1
+            && suffix.starts_with('.')
+            && !suffix.contains(['*', '?', '[', ']', '{', '}', '\\', '/']);
+        plain.then_some(suffix)
+    }
+
+    /// Extract `NAME` from a `**/NAME/**` pattern with a plain name
+    fn dir_name(pattern: &str) -> Option<&str> {
+        let name = pattern.strip_prefix("**/")?.strip_suffix("/**")?;
+        let plain = !name.is_empty()
+            && name != "."
+            && name != ".."
+            && !name.contains(['*', '?', '[', ']', '{', '}', '\\', '/']);
+        plain.then_some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATHS: &[&str] = &[
+        "/data/node_modules/a/index.js",
+        "/data/node_modules/",
+        "/data/node_modules",
+        "node_modules/x",
+        "/data/my_node_modules/x",
+        "/data/src/node_modules.rs",
+        "/data/target/debug/app",
+        "/data/.git/HEAD",
+        "/data/src/main.rs",
+        "/data/logs/app.log",
+        "/data/logs/app.log.gz",
+        "/data/.log",
+    ];
+
+    /// Check every path against the matcher and against plain `globset`
+    fn assert_agrees_with_globset(patterns: &[&str]) {
+        let matcher = PatternMatcher::new(patterns).unwrap();
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).unwrap());
+        }
+        let globs = builder.build().unwrap();
+
+        for path in PATHS {
+            assert_eq!(
+                matcher.is_match(Path::new(path)),
+                globs.is_match(path),
+                "{:?} against {:?}",
+                path,
+                patterns
+            );
+        }
+    }
+
+    #[test]
+    fn directory_names_use_the_fast_path() {
+        let matcher = PatternMatcher::new(&["**/node_modules/**", "**/.git/**"]).unwrap();
+        assert_eq!(matcher.dir_names.len(), 2);
+        assert!(matcher.globs.is_none());
+    }
+
+    #[test]
+    fn directory_fast_path_agrees_with_globset() {
+        assert_agrees_with_globset(&["**/node_modules/**"]);
+        assert_agrees_with_globset(&["**/.git/**", "**/target/**"]);
+        assert_agrees_with_globset(&["**/node_modules/**", "**/src/*.rs"]);
+    }
+
+    #[test]
+    fn extensions_use_the_fast_path() {
+        let matcher = PatternMatcher::new(&["*.log", "**/*.rs", "**/*.log"]).unwrap();
+        assert_eq!(matcher.suffixes, [b".log".to_vec(), b".rs".to_vec()]);
+        assert!(matcher.globs.is_none());
+    }
+
+    #[test]
+    fn extension_fast_path_agrees_with_globset() {
+        assert_agrees_with_globset(&["*.log"]);
+        assert_agrees_with_globset(&["**/*.rs", "*.gz"]);
+        // Each kind of pattern decides some of the paths
+        assert_agrees_with_globset(&["*.log", "**/target/**", "/data/src/*"]);
+        assert_agrees_with_globset(&["*.lo?", "*.{js,rs}"]);
+    }
+
+    #[test]
+    fn patterns_with_wildcards_are_globs() {
+        let matcher = PatternMatcher::new(&["**/node_*/**", "**/a/b/**"]).unwrap();
+        assert!(matcher.dir_names.is_empty());
+        assert_agrees_with_globset(&["**/node_*/**"]);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(matches!(
+            PatternMatcher::new(&["a[b"]),
+            Err(AnalyzerError::InvalidConfig(_))
+        ));
+    }
+}
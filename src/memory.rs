@@ -0,0 +1,89 @@
+// Process memory usage queries
+
+/// Get the peak resident set size of the current process, in bytes
+///
+/// The peak covers the whole process lifetime, so it includes anything the
+/// process did before a scan. Returns `None` when it can't be queried.
+#[cfg(unix)]
+pub fn peak_rss() -> Option<u64> {
+    // SAFETY: rusage is a plain C struct for which all-zero bytes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: usage points to a valid rusage
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    let max_rss = usage.ru_maxrss as u64;
+    // macOS reports bytes, other Unix systems kilobytes
+    let bytes = if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss.saturating_mul(1024)
+    };
+    (bytes > 0).then_some(bytes)
+}
+
+/// Get the peak working set size of the current process, in bytes
+#[cfg(windows)]
+pub fn peak_rss() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters = PROCESS_MEMORY_COUNTERS {
+        cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: the pseudo handle needs no closing and counters is valid with cb set
+    let ok = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, counters.cb) };
+    if ok == 0 {
+        return None;
+    }
+
+    Some(counters.PeakWorkingSetSize as u64)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn peak_rss() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scan;
+    use crate::{AnalyzerConfig, MemoryFileSystem};
+    use std::path::PathBuf;
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn peak_covers_touched_memory() {
+        const SIZE: usize = 64 * 1024 * 1024;
+        let before = peak_rss().unwrap();
+        assert!(before > 0);
+
+        // Touch every page so it becomes resident
+        let buffer = vec![1u8; SIZE];
+        assert_eq!(std::hint::black_box(&buffer)[SIZE - 1], 1);
+        let after = peak_rss().unwrap();
+        assert!(after >= before);
+        assert!(after >= SIZE as u64);
+    }
+
+    #[test]
+    fn peak_is_reported_only_when_tracked() {
+        let peak = |track_peak_memory| {
+            let mut fs = MemoryFileSystem::new();
+            fs.add_file("/data/a.txt", 1);
+            let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+            config.track_peak_memory = track_peak_memory;
+            scan(config, fs).metadata.peak_memory
+        };
+
+        assert_eq!(peak(false), None);
+        // Other tests may raise the peak in between, so only availability is compared
+        assert_eq!(peak(true).is_some(), peak_rss().is_some());
+    }
+}
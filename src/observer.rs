@@ -0,0 +1,178 @@
+// Scan event callbacks
+
+use crate::analyzer::FileEntry;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Receives scan events as the traversal produces them
+///
+/// Register implementations with [`FileAnalyzer::add_observer`] to log, drive
+/// a live UI or aggregate while a scan runs. Every method defaults to doing
+/// nothing. Observers may be called from several threads at once with the
+/// parallel strategy.
+///
+/// [`FileAnalyzer::add_observer`]: crate::FileAnalyzer::add_observer
+pub trait ScanObserver: Send + Sync {
+    /// A directory is about to be read
+    fn on_dir(&self, _path: &Path, _depth: usize) {}
+
+    /// A file passed every filter and was counted
+    fn on_file(&self, _entry: &FileEntry) {}
+
+    /// A warning was recorded
+    fn on_warning(&self, _warning: &str) {}
+}
+
+/// Estimated completion is capped below 1 until the scan actually finishes
+const MAX_ESTIMATE: f64 = 0.99;
+
+/// Snapshot of a running scan, passed to the progress callback
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanProgress {
+    pub files: usize,
+    /// Bytes counted so far, on-disk sizes when recorded
+    pub bytes: u64,
+    /// Share of the root volume's used space scanned so far, in `[0, 0.99]`
+    ///
+    /// `None` when the volume's capacity is unknown. Used space includes
+    /// data outside the scanned roots, so the estimate is a lower bound.
+    pub estimated_fraction: Option<f64>,
+}
+
+/// Callback invoked with the scan's progress after each counted file
+///
+/// With the parallel strategy it is called from several threads at once, so
+/// snapshots from different threads may arrive slightly out of order.
+pub type ProgressCallback = Arc<dyn Fn(&ScanProgress) + Send + Sync>;
+
+/// Observer turning counted files into progress snapshots
+pub(crate) struct ProgressObserver {
+    callback: ProgressCallback,
+    /// Used bytes on the root volume, the estimate's denominator
+    used_space: Option<u64>,
+    files: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl ProgressObserver {
+    pub(crate) fn new(callback: ProgressCallback, used_space: Option<u64>) -> Self {
+        Self {
+            callback,
+            used_space: used_space.filter(|&used| used > 0),
+            files: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ScanObserver for ProgressObserver {
+    fn on_file(&self, entry: &FileEntry) {
+        // Counters are atomic so the callback never runs under a lock
+        let size = entry.allocated_size.unwrap_or(entry.size);
+        let files = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+        let estimated_fraction = self
+            .used_space
+            .map(|used| (bytes as f64 / used as f64).min(MAX_ESTIMATE));
+        (self.callback)(&ScanProgress {
+            files,
+            bytes,
+            estimated_fraction,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnalyzerConfig, FileAnalyzer, MemoryFileSystem};
+    use std::path::PathBuf;
+
+    #[derive(Default)]
+    struct CountingObserver {
+        dirs: AtomicUsize,
+        files: AtomicUsize,
+        warnings: AtomicUsize,
+    }
+
+    impl ScanObserver for CountingObserver {
+        fn on_dir(&self, _path: &Path, _depth: usize) {
+            self.dirs.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_file(&self, _entry: &FileEntry) {
+            self.files.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_warning(&self, _warning: &str) {
+            self.warnings.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn observer_sees_every_event() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/a.txt", 1)
+            .add_file("/data/src/b.rs", 2)
+            .add_file("/data/src/lib/c.rs", 3)
+            .add_file("/data/locked/d.txt", 4)
+            .deny("/data/locked");
+        let observer = Arc::new(CountingObserver::default());
+
+        let mut analyzer = FileAnalyzer::with_file_system(
+            AnalyzerConfig::new(PathBuf::from("/data")),
+            Arc::new(fs),
+        );
+        analyzer.add_observer(observer.clone());
+        let result = analyzer.analyze().unwrap();
+
+        assert_eq!(observer.dirs.load(Ordering::Relaxed), 4);
+        assert_eq!(result.directory_count, 4);
+        assert_eq!(observer.files.load(Ordering::Relaxed), 3);
+        assert_eq!(
+            observer.warnings.load(Ordering::Relaxed),
+            result.warnings.len()
+        );
+        assert_eq!(result.warnings.len(), 1);
+    }
+
+    /// Feed files of the given sizes and collect the reported estimates
+    fn estimates(used_space: Option<u64>, sizes: &[u64]) -> Vec<Option<f64>> {
+        let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let observer = ProgressObserver::new(
+            Arc::new(move |progress: &ScanProgress| {
+                sink.lock().unwrap().push(progress.estimated_fraction);
+            }),
+            used_space,
+        );
+        for &size in sizes {
+            observer.on_file(&FileEntry {
+                path: PathBuf::from("/data/file"),
+                size,
+                depth: 2,
+                ..Default::default()
+            });
+        }
+        reported.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn estimate_is_capped_and_monotonic() {
+        let fractions: Vec<f64> = estimates(Some(100), &[30, 0, 30, 30, 30, 30])
+            .into_iter()
+            .map(Option::unwrap)
+            .collect();
+
+        assert_eq!(fractions, [0.3, 0.3, 0.6, 0.9, MAX_ESTIMATE, MAX_ESTIMATE]);
+        assert!(fractions.iter().all(|f| (0.0..=1.0).contains(f)));
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn estimate_needs_a_known_capacity() {
+        assert_eq!(estimates(None, &[10]), [None]);
+        assert_eq!(estimates(Some(0), &[10]), [None]);
+    }
+}
@@ -0,0 +1,26 @@
+// Report of directory names that collide on case-insensitive filesystems
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+
+pub struct CaseCollisionFormatter;
+
+impl OutputFormatter for CaseCollisionFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let collisions = result.case_colliding_dirs();
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== Case Collisions ({}) ===", collisions.len());
+        for group in collisions {
+            let _ = writeln!(out, "  {}", group[0].display());
+            for path in &group[1..] {
+                let _ = writeln!(out, "    also {}", path.display());
+            }
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,166 @@
+// Output compression codecs
+
+use crate::error::AnalyzerError;
+use std::io::Write;
+use std::path::Path;
+
+/// Compression applied to written output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    /// Requires the `zstd` feature
+    Zstd,
+    /// Requires the `brotli` feature
+    Brotli,
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            "brotli" | "br" => Ok(Compression::Brotli),
+            _ => Err(format!("Invalid compression: {}", s)),
+        }
+    }
+}
+
+impl Compression {
+    /// Infer the compression from an output file extension
+    /// (`.gz`, `.zst`, `.br`), defaulting to none
+    pub fn from_extension(path: &Path) -> Self {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            Some("br") => Compression::Brotli,
+            _ => Compression::None,
+        }
+    }
+
+    /// Check that the codec was compiled in
+    pub fn ensure_available(self) -> Result<(), AnalyzerError> {
+        let codec = match self {
+            Compression::Zstd if !cfg!(feature = "zstd") => "zstd",
+            Compression::Brotli if !cfg!(feature = "brotli") => "brotli",
+            _ => return Ok(()),
+        };
+        Err(AnalyzerError::Output(format!(
+            "{} compression requires building with the `{}` feature",
+            codec, codec
+        )))
+    }
+
+    /// Stream `data` through the encoder into `writer`, finishing the
+    /// compressed stream
+    pub fn write_all<W: Write>(self, mut writer: W, data: &[u8]) -> Result<(), AnalyzerError> {
+        match self {
+            Compression::None => {
+                writer.write_all(data)?;
+                writer.flush()?;
+            }
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?.flush()?;
+            }
+            Compression::Zstd => Self::write_zstd(writer, data)?,
+            Compression::Brotli => Self::write_brotli(writer, data)?,
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
+    fn write_zstd<W: Write>(writer: W, data: &[u8]) -> Result<(), AnalyzerError> {
+        let mut encoder = zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        encoder.write_all(data)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn write_zstd<W: Write>(_writer: W, _data: &[u8]) -> Result<(), AnalyzerError> {
+        Compression::Zstd.ensure_available()
+    }
+
+    #[cfg(feature = "brotli")]
+    fn write_brotli<W: Write>(writer: W, data: &[u8]) -> Result<(), AnalyzerError> {
+        // Buffer size, quality (0-11) and window size (log2) of the encoder
+        let mut encoder = brotli::CompressorWriter::new(writer, 64 * 1024, 9, 22);
+        encoder.write_all(data)?;
+        // Taking the writer back finishes the stream
+        encoder.into_inner().flush()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "brotli"))]
+    fn write_brotli<W: Write>(_writer: W, _data: &[u8]) -> Result<(), AnalyzerError> {
+        Compression::Brotli.ensure_available()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    const DATA: &[u8] = b"{\"inputs\":{},\"outputs\":{}}\n";
+
+    fn compress(compression: Compression) -> Vec<u8> {
+        let mut out = Vec::new();
+        compression.write_all(&mut out, DATA).unwrap();
+        out
+    }
+
+    #[test]
+    fn gzip_round_trips() {
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(compress(Compression::Gzip).as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, DATA);
+        assert_eq!(compress(Compression::None), DATA);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trips() {
+        let decoded = zstd::decode_all(compress(Compression::Zstd).as_slice()).unwrap();
+        assert_eq!(decoded, DATA);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn brotli_round_trips() {
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(compress(Compression::Brotli).as_slice(), 4096)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, DATA);
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn missing_codec_is_reported() {
+        let error = Compression::Zstd.write_all(Vec::new(), DATA).unwrap_err();
+        assert!(error.to_string().contains("`zstd` feature"));
+    }
+
+    #[test]
+    fn codec_is_inferred_from_extension() {
+        for (name, compression) in [
+            ("out.json.gz", Compression::Gzip),
+            ("out.json.ZST", Compression::Zstd),
+            ("out.json.br", Compression::Brotli),
+            ("out.json", Compression::None),
+        ] {
+            assert_eq!(Compression::from_extension(Path::new(name)), compression);
+        }
+    }
+}
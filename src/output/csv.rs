@@ -0,0 +1,220 @@
+// CSV formatter with selectable columns
+
+use crate::analyzer::{AnalysisResult, FileEntry, normalize_separators};
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::time::UNIX_EPOCH;
+
+/// A field of a file entry that can be written as a CSV column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumn {
+    Path,
+    Size,
+    /// On-disk size, empty unless allocated sizes were recorded
+    Allocated,
+    /// Modification time in seconds since the Unix epoch, empty unless recorded
+    Mtime,
+    Depth,
+    /// Owning uid, empty unless owners were captured
+    Owner,
+    /// Owning gid, empty unless owners were captured
+    Group,
+    Symlink,
+    Target,
+    ContentType,
+}
+
+impl CsvColumn {
+    /// Every column, in the order they are listed in help text
+    pub const ALL: [CsvColumn; 10] = [
+        CsvColumn::Path,
+        CsvColumn::Size,
+        CsvColumn::Allocated,
+        CsvColumn::Mtime,
+        CsvColumn::Depth,
+        CsvColumn::Owner,
+        CsvColumn::Group,
+        CsvColumn::Symlink,
+        CsvColumn::Target,
+        CsvColumn::ContentType,
+    ];
+
+    /// Column name as accepted by `--csv-columns` and written in the header
+    pub fn name(self) -> &'static str {
+        match self {
+            CsvColumn::Path => "path",
+            CsvColumn::Size => "size",
+            CsvColumn::Allocated => "allocated",
+            CsvColumn::Mtime => "mtime",
+            CsvColumn::Depth => "depth",
+            CsvColumn::Owner => "owner",
+            CsvColumn::Group => "group",
+            CsvColumn::Symlink => "symlink",
+            CsvColumn::Target => "target",
+            CsvColumn::ContentType => "content_type",
+        }
+    }
+
+    /// Parse a comma-separated column list, e.g. `path,size,mtime`
+    pub fn parse_list(s: &str) -> Result<Vec<CsvColumn>, String> {
+        s.split(',').map(|name| name.trim().parse()).collect()
+    }
+}
+
+impl std::str::FromStr for CsvColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        CsvColumn::ALL
+            .into_iter()
+            .find(|column| column.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = CsvColumn::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "Invalid CSV column: {} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Writes one row per file with a header row naming the columns
+#[derive(Debug, Clone)]
+pub struct CsvFormatter {
+    pub columns: Vec<CsvColumn>,
+}
+
+impl Default for CsvFormatter {
+    fn default() -> Self {
+        Self {
+            columns: vec![CsvColumn::Path, CsvColumn::Size],
+        }
+    }
+}
+
+impl CsvFormatter {
+    fn field(&self, entry: &FileEntry, column: CsvColumn) -> String {
+        match column {
+            CsvColumn::Path => normalize_separators(&entry.path),
+            CsvColumn::Size => entry.size.to_string(),
+            CsvColumn::Allocated => optional(entry.allocated_size),
+            CsvColumn::Mtime => optional(
+                entry
+                    .modified
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+            ),
+            CsvColumn::Depth => entry.depth.to_string(),
+            CsvColumn::Owner => optional(entry.uid),
+            CsvColumn::Group => optional(entry.gid),
+            CsvColumn::Symlink => entry.is_symlink.to_string(),
+            CsvColumn::Target => entry
+                .target
+                .as_deref()
+                .map(|target| target.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            CsvColumn::ContentType => entry.content_type.clone().unwrap_or_default(),
+        }
+    }
+
+    fn push_row<I: IntoIterator<Item = String>>(out: &mut String, fields: I) {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&escape(&field));
+        }
+        out.push('\n');
+    }
+}
+
+impl OutputFormatter for CsvFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+        Self::push_row(
+            &mut out,
+            self.columns.iter().map(|column| column.name().to_string()),
+        );
+        for entry in &result.entries {
+            Self::push_row(
+                &mut out,
+                self.columns.iter().map(|&column| self.field(entry, column)),
+            );
+        }
+        Ok(out)
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quote a field when it contains a separator, quote or line break (RFC 4180)
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::SortOrder;
+    use crate::test_support::{entry, result_of};
+    use std::time::Duration;
+
+    #[test]
+    fn selected_columns_are_written_in_order() {
+        let mut dated = entry("/data/a, b.txt", 10);
+        dated.modified = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let result = result_of(vec![dated, entry("/data/src/c.rs", 20)]);
+        let formatter = CsvFormatter {
+            columns: CsvColumn::parse_list("depth, MTIME,path").unwrap(),
+        };
+
+        assert_eq!(
+            formatter.format(&result).unwrap(),
+            "depth,mtime,path\n\
+             2,1700000000,\"/data/a, b.txt\"\n\
+             3,,/data/src/c.rs\n"
+        );
+    }
+
+    #[test]
+    fn rows_follow_the_sort_order() {
+        let result = result_of(vec![
+            entry("/data/b", 2),
+            entry("/data/c", 1),
+            entry("/data/a", 2),
+        ]);
+        let rows = |order: &str| {
+            let mut sorted = result_of(result.entries.clone());
+            order
+                .parse::<SortOrder>()
+                .unwrap()
+                .sort(&mut sorted.entries);
+            CsvFormatter::default().format(&sorted).unwrap()
+        };
+
+        assert_eq!(rows("none"), "path,size\n/data/b,2\n/data/c,1\n/data/a,2\n");
+        assert_eq!(
+            rows("size-desc"),
+            "path,size\n/data/a,2\n/data/b,2\n/data/c,1\n"
+        );
+        assert_eq!(
+            rows("size-asc"),
+            "path,size\n/data/c,1\n/data/a,2\n/data/b,2\n"
+        );
+    }
+
+    #[test]
+    fn unknown_columns_are_rejected() {
+        let error = CsvColumn::parse_list("path,bytes").unwrap_err();
+        assert!(error.starts_with("Invalid CSV column: bytes"));
+        assert!(CsvColumn::parse_list("").is_err());
+    }
+}
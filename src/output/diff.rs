@@ -0,0 +1,47 @@
+// Report of changes against an earlier scan
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+
+pub struct DiffFormatter {
+    /// Result of the earlier scan to compare against
+    pub previous: AnalysisResult,
+    /// Only report resized files whose size changed by at least this many bytes
+    pub min_delta: u64,
+}
+
+impl OutputFormatter for DiffFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let diffs = result.diff(&self.previous, self.min_delta);
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== Changes ({}) ===", diffs.len());
+        let _ = writeln!(
+            out,
+            "Total Size: {} -> {} bytes ({:+})",
+            self.previous.total_size,
+            result.total_size,
+            result.total_size as i128 - self.previous.total_size as i128
+        );
+        for diff in diffs {
+            let _ = match (diff.old_size, diff.new_size) {
+                (None, Some(new)) => writeln!(out, "  + {} bytes: {}", new, diff.path.display()),
+                (Some(old), None) => writeln!(out, "  - {} bytes: {}", old, diff.path.display()),
+                (Some(old), Some(new)) => writeln!(
+                    out,
+                    "  ~ {:+} bytes ({} -> {}): {}",
+                    diff.delta(),
+                    old,
+                    new,
+                    diff.path.display()
+                ),
+                (None, None) => Ok(()),
+            };
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,36 @@
+// Per-extension size report formatter
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::{OutputFormatter, format_size};
+use std::fmt::Write;
+
+pub struct ExtensionFormatter;
+
+impl OutputFormatter for ExtensionFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== Size by Extension ===");
+        let _ = writeln!(out);
+        for stats in result.extension_stats() {
+            let name = if stats.extension.is_empty() {
+                "(none)".to_string()
+            } else {
+                format!(".{}", stats.extension)
+            };
+            let _ = writeln!(
+                out,
+                "  {}: {} files, {} total, {} mean, {} median",
+                name,
+                stats.file_count,
+                format_size(stats.total_size),
+                format_size(stats.mean_size.round() as u64),
+                format_size(stats.median_size.round() as u64)
+            );
+        }
+
+        Ok(out)
+    }
+}
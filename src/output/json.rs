@@ -0,0 +1,13 @@
+// JSON output formatter (full analysis result)
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+
+pub struct JsonFormatter;
+
+impl OutputFormatter for JsonFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        Ok(serde_json::to_string_pretty(result)?)
+    }
+}
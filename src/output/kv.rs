@@ -0,0 +1,93 @@
+// Flat key=value formatter for shell scripts
+
+use crate::analyzer::{AnalysisResult, normalize_separators};
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+
+/// Writes one `key=value` summary line per figure, then optional entry lines
+///
+/// Entry lines are `size=N path=P`; the path comes last and runs to the end
+/// of the line, so it may contain spaces or `=`.
+#[derive(Debug, Clone, Default)]
+pub struct KvFormatter {
+    /// Add a line per file after the summary
+    pub entries: bool,
+}
+
+impl OutputFormatter for KvFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "total_size={}", result.total_size);
+        let _ = writeln!(out, "total_allocated_size={}", result.total_allocated_size);
+        let _ = writeln!(out, "file_count={}", result.file_count);
+        let _ = writeln!(out, "directory_count={}", result.directory_count);
+        let _ = writeln!(out, "symlink_count={}", result.symlink_count);
+        let _ = writeln!(out, "warning_count={}", result.warnings.len());
+        let _ = writeln!(out, "incomplete={}", result.incomplete);
+        let metadata = &result.metadata;
+        for (key, value) in [
+            ("disk_total", metadata.disk_total),
+            ("disk_free", metadata.disk_free),
+            ("seed", metadata.seed),
+            ("peak_memory", metadata.peak_memory),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "{}={}", key, value);
+            }
+        }
+
+        if self.entries {
+            for entry in &result.entries {
+                let _ = writeln!(
+                    out,
+                    "size={} path={}",
+                    entry.size,
+                    normalize_separators(&entry.path)
+                );
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, result_of};
+    use std::collections::HashMap;
+
+    #[test]
+    fn summary_keys_match_the_result() {
+        let mut result = result_of(vec![entry("/data/a.txt", 10), entry("/data/b c=d", 20)]);
+        result.directory_count = 1;
+        result.metadata.seed = Some(42);
+        let text = KvFormatter::default().format(&result).unwrap();
+
+        let values: HashMap<&str, &str> = text
+            .lines()
+            .map(|line| line.split_once('=').unwrap())
+            .collect();
+        assert_eq!(values["total_size"], "30");
+        assert_eq!(values["total_allocated_size"], "0");
+        assert_eq!(values["file_count"], "2");
+        assert_eq!(values["directory_count"], "1");
+        assert_eq!(values["symlink_count"], "0");
+        assert_eq!(values["warning_count"], "0");
+        assert_eq!(values["incomplete"], "false");
+        assert_eq!(values["seed"], "42");
+        assert!(!values.contains_key("disk_total"));
+        assert!(!text.contains("path="));
+    }
+
+    #[test]
+    fn entry_lines_end_with_the_path() {
+        let result = result_of(vec![entry("/data/b c=d", 20)]);
+        let formatter = KvFormatter { entries: true };
+        let text = formatter.format(&result).unwrap();
+        assert_eq!(text.lines().last(), Some("size=20 path=/data/b c=d"));
+    }
+}
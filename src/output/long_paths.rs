@@ -0,0 +1,44 @@
+// Report of paths that exceed portable length limits
+
+use crate::analyzer::{AnalysisResult, PathLengthLimit};
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Default)]
+pub struct LongPathFormatter {
+    pub limit: PathLengthLimit,
+}
+
+impl OutputFormatter for LongPathFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let long_paths = result.long_paths(self.limit);
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            out,
+            "=== Long Paths ({}, limits: path {}, name {}) ===",
+            long_paths.len(),
+            self.limit.max_path,
+            self.limit.max_component
+        );
+        for entry in long_paths {
+            let longest_name = entry
+                .path
+                .components()
+                .map(|c| c.as_os_str().len())
+                .max()
+                .unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "  [path {}, name {}] {}",
+                entry.path.as_os_str().len(),
+                longest_name,
+                entry.path.display()
+            );
+        }
+
+        Ok(out)
+    }
+}
@@ -1,12 +1,13 @@
 // Metafile output formatter (esbuild compatible)
 // Based on bloaty-metafile conversion logic
 
-use crate::analyzer::AnalysisResult;
+use crate::analyzer::{AnalysisResult, FileEntry, normalize_separators};
 use crate::error::AnalyzerError;
 use crate::output::OutputFormatter;
-use serde_metafile::{Import, Input, InputDetail, Metafile, Output};
+use serde_metafile::{Import, ImportKind, Input, InputDetail, Metafile, Output};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
 
 #[derive(Debug, Clone, Default)]
 struct TreeNode {
@@ -14,36 +15,133 @@ struct TreeNode {
     size: u64,
     total_size: u64,
     children: HashMap<String, TreeNode>,
+    /// Attributes for the input's `with` map (files only)
+    with: Option<HashMap<String, String>>,
 }
 
-pub struct MetafileFormatter;
+#[derive(Debug, Clone, Default)]
+pub struct MetafileFormatter {
+    /// Tag import edges by child type: directories as `import-statement`,
+    /// files as `require-call`, so viewers can style them differently
+    pub import_kinds: bool,
+    /// Scan roots to add as summary inputs, keyed by the root path with a
+    /// trailing `/` and holding the total bytes found under the root
+    pub root_inputs: Vec<PathBuf>,
+    /// Fill each file input's `with` map with its `mtime` (seconds since the
+    /// Unix epoch, when recorded) and `is_symlink`
+    ///
+    /// esbuild ignores `with`, but custom viewers can show it.
+    pub file_metadata: bool,
+    /// Separator between tree levels in entry paths, `/` when unset
+    ///
+    /// Lets logical hierarchies such as `crate::module::item` keys from a
+    /// manifest nest like directories.
+    pub separator: Option<String>,
+}
 
 impl MetafileFormatter {
-    /// Normalize path separators to forward slashes for consistency
-    fn normalize_path(path: &Path) -> String {
-        path.display().to_string().replace('\\', "/")
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn separator(&self) -> &str {
+        self.separator
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .unwrap_or("/")
     }
 
     /// Build a tree structure from file entries
-    fn build_tree(result: &AnalysisResult) -> TreeNode {
+    fn build_tree(&self, result: &AnalysisResult) -> TreeNode {
         let mut root = TreeNode {
             name: "ROOT".to_string(),
             size: 0,
             total_size: 0,
             children: HashMap::new(),
+            with: None,
         };
 
         for entry in &result.entries {
-            let path_str = Self::normalize_path(&entry.path);
-            let parts: Vec<String> = path_str.split('/').map(String::from).collect();
-            Self::add_path(&mut root, &parts, entry.size);
+            let path_str = normalize_separators(&entry.path);
+            let parts: Vec<String> = path_str.split(self.separator()).map(String::from).collect();
+            let with = self.file_metadata.then(|| Self::entry_with(entry));
+            Self::add_path(&mut root, &parts, Self::entry_size(entry), with);
         }
 
         root
     }
 
+    /// Per-file attributes for the `with` map
+    fn entry_with(entry: &FileEntry) -> HashMap<String, String> {
+        let mut with = HashMap::from([("is_symlink".to_string(), entry.is_symlink.to_string())]);
+        if let Some(secs) = entry
+            .modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+        {
+            with.insert("mtime".to_string(), secs.to_string());
+        }
+        with
+    }
+
+    /// Size of an entry in the tree, preferring the on-disk size when it was recorded
+    ///
+    /// Allocated sizes are recorded for every entry of an allocated-mode scan
+    /// and none otherwise, so the tree adds up to `total_allocated_size` or
+    /// `total_size` respectively.
+    fn entry_size(entry: &FileEntry) -> u64 {
+        entry.allocated_size.unwrap_or(entry.size)
+    }
+
+    /// Add a summary input for each configured scan root
+    ///
+    /// These are not added to the output, so they don't inflate its size.
+    fn add_root_inputs(&self, result: &AnalysisResult, inputs: &mut HashMap<String, Input>) {
+        for root_path in &self.root_inputs {
+            // A root made relative to itself is empty
+            let path = match normalize_separators(root_path) {
+                path if path.is_empty() => ".".to_string(),
+                path => path,
+            };
+            let path = path.trim_end_matches('/');
+            let bytes = result
+                .entries
+                .iter()
+                .filter(|e| e.path.starts_with(root_path))
+                .map(Self::entry_size)
+                .sum();
+
+            let imports = inputs
+                .contains_key(path)
+                .then(|| Import {
+                    path: path.to_string(),
+                    kind: None,
+                    external: false,
+                    original: None,
+                    with: None,
+                })
+                .into_iter()
+                .collect();
+
+            inputs.insert(
+                format!("{}/", path),
+                Input {
+                    bytes,
+                    imports,
+                    format: None,
+                    with: None,
+                },
+            );
+        }
+    }
+
     /// Add a path to the tree, accumulating sizes
-    fn add_path(node: &mut TreeNode, parts: &[String], size: u64) {
+    fn add_path(
+        node: &mut TreeNode,
+        parts: &[String],
+        size: u64,
+        with: Option<HashMap<String, String>>,
+    ) {
         node.total_size += size;
 
         if parts.is_empty() {
@@ -60,9 +158,11 @@ impl MetafileFormatter {
                 size,
                 total_size: size,
                 children: HashMap::new(),
+                with: None,
             });
             child.size = size;
             child.total_size = size;
+            child.with = with;
         } else {
             // This is a directory (intermediate node)
             let child = node.children.entry(part.clone()).or_insert(TreeNode {
@@ -70,19 +170,35 @@ impl MetafileFormatter {
                 size: 0,
                 total_size: 0,
                 children: HashMap::new(),
+                with: None,
             });
-            Self::add_path(child, remaining, size);
+            Self::add_path(child, remaining, size, with);
+        }
+    }
+
+    /// Get the import kind for an edge to the given child node
+    fn import_kind(&self, child: &TreeNode) -> Option<ImportKind> {
+        if !self.import_kinds {
+            return None;
+        }
+
+        if child.children.is_empty() {
+            Some(ImportKind::RequireCall)
+        } else {
+            Some(ImportKind::ImportStatement)
         }
     }
 
     /// Traverse the tree and generate metafile inputs
     fn traverse_tree(
+        &self,
         node: &TreeNode,
         inputs: &mut HashMap<String, Input>,
         parent_path: Option<String>,
     ) {
+        let separator = self.separator();
         let full_path = match &parent_path {
-            Some(p) => format!("{}/{}", p, node.name),
+            Some(p) => format!("{}{}{}", p, separator, node.name),
             None => node.name.clone(),
         };
 
@@ -91,8 +207,8 @@ impl MetafileFormatter {
             .children
             .values()
             .map(|child| Import {
-                path: format!("{}/{}", full_path, child.name),
-                kind: None,
+                path: format!("{}{}{}", full_path, separator, child.name),
+                kind: self.import_kind(child),
                 external: false,
                 original: None,
                 with: None,
@@ -104,27 +220,29 @@ impl MetafileFormatter {
             bytes: node.size,
             imports,
             format: None,
-            with: None,
+            with: node.with.clone(),
         };
 
         inputs.insert(full_path.clone(), input);
 
         // Recursively traverse children
         for child in node.children.values() {
-            Self::traverse_tree(child, inputs, Some(full_path.clone()));
+            self.traverse_tree(child, inputs, Some(full_path.clone()));
         }
     }
-}
 
-impl OutputFormatter for MetafileFormatter {
-    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+    /// Convert an analysis result to a structured metafile
+    ///
+    /// Useful for merging, filtering or re-serializing before writing;
+    /// [`format`](OutputFormatter::format) serializes exactly this.
+    pub fn to_metafile(&self, result: &AnalysisResult) -> Metafile {
         // Build tree structure
-        let root = Self::build_tree(result);
+        let root = self.build_tree(result);
 
         // Generate inputs by traversing the tree
         let mut inputs = HashMap::new();
         for child in root.children.values() {
-            Self::traverse_tree(child, &mut inputs, None);
+            self.traverse_tree(child, &mut inputs, None);
         }
 
         // Create output entry with all inputs
@@ -140,6 +258,8 @@ impl OutputFormatter for MetafileFormatter {
             })
             .collect();
 
+        self.add_root_inputs(result, &mut inputs);
+
         let output = Output {
             bytes: root.total_size,
             inputs: output_inputs,
@@ -150,7 +270,13 @@ impl OutputFormatter for MetafileFormatter {
         };
 
         let outputs = HashMap::from([("wiztree".to_string(), output)]);
-        let metafile = Metafile { inputs, outputs };
+        Metafile { inputs, outputs }
+    }
+}
+
+impl OutputFormatter for MetafileFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let metafile = self.to_metafile(result);
 
         // Use compact JSON format to reduce file size
         let json = serde_json::to_string(&metafile)?;
@@ -180,3 +306,168 @@ impl OutputFormatter for MetafileFormatter {
         Ok(json)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, result_of};
+
+    fn result() -> AnalysisResult {
+        result_of(vec![
+            entry("/data/a.txt", 10),
+            entry("/data/src/b.rs", 20),
+            entry("/data/src/lib/c.rs", 30),
+        ])
+    }
+
+    fn import_kind<'a>(metafile: &'a Metafile, from: &str, to: &str) -> Option<&'a ImportKind> {
+        metafile.inputs[from]
+            .imports
+            .iter()
+            .find(|import| import.path == to)
+            .and_then(|import| import.kind.as_ref())
+    }
+
+    #[test]
+    fn import_kinds_tell_directories_from_files() {
+        let formatter = MetafileFormatter {
+            import_kinds: true,
+            ..Default::default()
+        };
+        let metafile = formatter.to_metafile(&result());
+
+        assert!(matches!(
+            import_kind(&metafile, "/data/src", "/data/src/lib"),
+            Some(ImportKind::ImportStatement)
+        ));
+        assert!(matches!(
+            import_kind(&metafile, "/data/src", "/data/src/b.rs"),
+            Some(ImportKind::RequireCall)
+        ));
+    }
+
+    #[test]
+    fn import_kinds_are_omitted_by_default() {
+        let metafile = MetafileFormatter::new().to_metafile(&result());
+        assert!(
+            metafile
+                .inputs
+                .values()
+                .flat_map(|input| &input.imports)
+                .all(|import| import.kind.is_none())
+        );
+    }
+
+    #[test]
+    fn root_input_holds_the_total_size() {
+        let result = result();
+        let formatter = MetafileFormatter {
+            root_inputs: vec![PathBuf::from("/data")],
+            ..Default::default()
+        };
+        let metafile = formatter.to_metafile(&result);
+
+        let root = &metafile.inputs["/data/"];
+        assert_eq!(root.bytes, result.total_size);
+        assert_eq!(root.imports.len(), 1);
+        assert_eq!(root.imports[0].path, "/data");
+        // The summary input is not part of the output
+        assert!(!metafile.outputs["wiztree"].inputs.contains_key("/data/"));
+    }
+
+    #[test]
+    fn root_input_of_a_relativized_root() {
+        let result = result_of(vec![entry("a.txt", 1), entry("src/b.rs", 2)]);
+        let formatter = MetafileFormatter {
+            root_inputs: vec![PathBuf::new()],
+            ..Default::default()
+        };
+        let metafile = formatter.to_metafile(&result);
+        assert_eq!(metafile.inputs["./"].bytes, 3);
+    }
+
+    #[test]
+    fn output_bytes_match_the_result() {
+        let result = result();
+        let metafile = MetafileFormatter::new().to_metafile(&result);
+
+        let output = &metafile.outputs["wiztree"];
+        assert_eq!(output.bytes, result.total_size);
+        assert_eq!(metafile.inputs["/data/src/lib/c.rs"].bytes, 30);
+        assert_eq!(output.inputs["/data/src/b.rs"].bytes_in_output, 20);
+
+        // format() writes the same metafile
+        let json: serde_json::Value =
+            serde_json::from_str(&MetafileFormatter::new().format(&result).unwrap()).unwrap();
+        assert_eq!(json["outputs"]["wiztree"]["bytes"], result.total_size);
+    }
+
+    #[test]
+    fn output_bytes_prefer_allocated_sizes() {
+        let mut entries = vec![entry("/data/a.txt", 10), entry("/data/b.txt", 20)];
+        for entry in &mut entries {
+            entry.allocated_size = Some(4096);
+        }
+        let result = result_of(entries);
+        let metafile = MetafileFormatter::new().to_metafile(&result);
+        assert_eq!(
+            metafile.outputs["wiztree"].bytes,
+            result.total_allocated_size
+        );
+    }
+
+    #[test]
+    fn file_metadata_fills_the_with_map() {
+        let mut link = entry("/data/link", 4);
+        link.is_symlink = true;
+        link.modified = Some(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+        let result = result_of(vec![entry("/data/a.txt", 10), link]);
+        let formatter = MetafileFormatter {
+            file_metadata: true,
+            ..Default::default()
+        };
+        let metafile = formatter.to_metafile(&result);
+
+        let with = metafile.inputs["/data/link"].with.as_ref().unwrap();
+        assert_eq!(with["is_symlink"], "true");
+        assert_eq!(with["mtime"], "1700000000");
+        let with = metafile.inputs["/data/a.txt"].with.as_ref().unwrap();
+        assert_eq!(with["is_symlink"], "false");
+        assert!(!with.contains_key("mtime"));
+        // Directories have no attributes
+        assert!(metafile.inputs["/data"].with.is_none());
+
+        let plain = MetafileFormatter::new().to_metafile(&result);
+        assert!(plain.inputs.values().all(|input| input.with.is_none()));
+    }
+
+    #[test]
+    fn custom_separator_nests_logical_paths() {
+        let result = result_of(vec![
+            entry("serde::de::Visitor", 5),
+            entry("serde::de::Deserializer", 7),
+            entry("serde::Serialize", 11),
+        ]);
+        let formatter = MetafileFormatter {
+            separator: Some("::".to_string()),
+            ..Default::default()
+        };
+        let metafile = formatter.to_metafile(&result);
+
+        let mut imports: Vec<&str> = metafile.inputs["serde::de"]
+            .imports
+            .iter()
+            .map(|import| import.path.as_str())
+            .collect();
+        imports.sort();
+        assert_eq!(imports, ["serde::de::Deserializer", "serde::de::Visitor"]);
+        assert!(
+            metafile.inputs["serde"]
+                .imports
+                .iter()
+                .any(|import| import.path == "serde::de")
+        );
+        assert_eq!(metafile.inputs["serde::Serialize"].bytes, 11);
+        assert_eq!(metafile.outputs["wiztree"].bytes, 23);
+    }
+}
@@ -1,24 +1,173 @@
 // Output formatters
 
-use crate::analyzer::AnalysisResult;
+use crate::analyzer::{AnalysisResult, FileEntry, WarningKind};
 use crate::error::AnalyzerError;
-use std::fs::File;
-use std::io::Write;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
 use std::path::Path;
+pub mod case;
+pub mod compress;
+pub mod csv;
+pub mod diff;
+pub mod extension;
+pub mod json;
+pub mod kv;
+pub mod long_paths;
 pub mod metafile;
+pub mod ndjson;
+pub mod owner;
+pub mod paths;
+pub mod recent;
+pub mod small_files;
+pub mod stats;
+pub mod text;
+pub mod url;
+pub use case::CaseCollisionFormatter;
+pub use compress::Compression;
+pub use csv::{CsvColumn, CsvFormatter};
+pub use diff::DiffFormatter;
+pub use extension::ExtensionFormatter;
+pub use json::JsonFormatter;
+pub use kv::KvFormatter;
+pub use long_paths::LongPathFormatter;
 pub use metafile::MetafileFormatter;
+pub use ndjson::{FlushPolicy, FlushTimer, NdjsonWriter};
+pub use owner::OwnerFormatter;
+pub use paths::PathListFormatter;
+pub use recent::RecentDirsFormatter;
+pub use small_files::SmallFileHotspotFormatter;
+pub use stats::StatsFormatter;
+pub use text::TextFormatter;
+pub use url::AnalyzeUrlFormatter;
 
 /// Trait for formatting analysis results
 pub trait OutputFormatter {
     fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError>;
 }
 
+/// Format a byte count with a binary unit (e.g. `1.18 MiB`)
+///
+/// Units are labeled `KiB`, `MiB`, ... as accepted by [`parse_size`], since
+/// `KB`, `MB`, ... are decimal there.
+///
+/// [`parse_size`]: crate::config::parse_size
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["bytes", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
+
+/// Summary line comparing the analyzed size with the disk's capacity, when
+/// the capacity was recorded
+pub(crate) fn disk_line(result: &AnalysisResult) -> Option<String> {
+    let (total, free) = (result.metadata.disk_total?, result.metadata.disk_free?);
+    Some(format!(
+        "Disk: analyzed {} of {}, {} free",
+        format_size(result.total_size),
+        format_size(total),
+        format_size(free)
+    ))
+}
+
+/// Summary line with the scan's peak memory use, when it was tracked
+pub(crate) fn peak_memory_line(result: &AnalysisResult) -> Option<String> {
+    let peak = result.metadata.peak_memory?;
+    Some(format!("Peak Memory: {}", format_size(peak)))
+}
+
 /// Output format type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum OutputFormat {
     Text,
     Json,
+    #[default]
     Metafile,
+    /// Gzipped, base64-encoded metafile embedded in a URL
+    EsbuildAnalyzeUrl,
+    /// One row per file with the columns chosen by [`CsvColumn`]
+    Csv,
+    /// `key=value` summary lines for shell scripts
+    Kv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "metafile" => Ok(OutputFormat::Metafile),
+            "esbuild-analyze-url" | "url" => Ok(OutputFormat::EsbuildAnalyzeUrl),
+            "csv" => Ok(OutputFormat::Csv),
+            "kv" => Ok(OutputFormat::Kv),
+            _ => Err(format!("Invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Order of entries in the written output
+///
+/// Applied after collection, so output stays reproducible whatever order the
+/// traversal discovered entries in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Keep discovery order
+    #[default]
+    None,
+    Path,
+    SizeDesc,
+    SizeAsc,
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(SortOrder::None),
+            "path" => Ok(SortOrder::Path),
+            "size-desc" => Ok(SortOrder::SizeDesc),
+            "size-asc" => Ok(SortOrder::SizeAsc),
+            _ => Err(format!("Invalid sort order: {}", s)),
+        }
+    }
+}
+
+impl SortOrder {
+    /// Sort entries in place; ties in size are broken by path
+    pub fn sort(self, entries: &mut [FileEntry]) {
+        match self {
+            SortOrder::None => {}
+            SortOrder::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+            SortOrder::SizeDesc => {
+                entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)))
+            }
+            SortOrder::SizeAsc => {
+                entries.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path)))
+            }
+        }
+    }
+}
+
+/// A collected warning as written by [`OutputWriter::write_warnings`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WarningRecord<'a> {
+    pub kind: WarningKind,
+    pub path: Option<&'a Path>,
+    pub message: &'a str,
 }
 
 /// Writes analysis results to stdout or file
@@ -26,17 +175,216 @@ pub struct OutputWriter;
 
 impl OutputWriter {
     pub fn write(result: &AnalysisResult, output_path: Option<&Path>) -> Result<(), AnalyzerError> {
-        let formatter = MetafileFormatter;
+        Self::write_with(
+            &MetafileFormatter::new(),
+            result,
+            output_path,
+            false,
+            Compression::None,
+        )
+    }
+
+    /// Write analysis results using the given formatter
+    ///
+    /// On stdout, a trailing newline is added to uncompressed output unless it
+    /// already ends with a record terminator (newline or NUL). With
+    /// `create_dirs`, missing parent directories of the output file are
+    /// created.
+    pub fn write_with(
+        formatter: &dyn OutputFormatter,
+        result: &AnalysisResult,
+        output_path: Option<&Path>,
+        create_dirs: bool,
+        compression: Compression,
+    ) -> Result<(), AnalyzerError> {
+        compression.ensure_available()?;
         let s = formatter.format(result)?;
         match output_path {
             Some(path) => {
-                let mut file = File::create(path)?;
-                file.write_all(s.as_bytes())?;
+                let file = Self::create_file(path, create_dirs)?;
+                compression.write_all(BufWriter::new(file), s.as_bytes())?;
+            }
+            _ if compression != Compression::None => {
+                compression.write_all(std::io::stdout().lock(), s.as_bytes())?;
             }
             _ => {
-                println!("{s}");
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(s.as_bytes())?;
+                if !s.ends_with(['\n', '\0']) {
+                    stdout.write_all(b"\n")?;
+                }
+                stdout.flush()?;
             }
         }
         Ok(())
     }
+
+    /// Write the collected warnings to a file as a JSON array of objects
+    ///
+    /// Kept apart from the main output so CI can annotate warnings without
+    /// parsing the report. Warnings without recorded details, e.g. from a
+    /// result saved by an older version, have kind `other` and no path.
+    pub fn write_warnings(
+        result: &AnalysisResult,
+        path: &Path,
+        create_dirs: bool,
+    ) -> Result<(), AnalyzerError> {
+        let records: Vec<WarningRecord> = result
+            .warnings
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                let detail = result.warning_details.get(index);
+                WarningRecord {
+                    kind: detail.map(|d| d.kind).unwrap_or_default(),
+                    path: detail.and_then(|d| d.path.as_deref()),
+                    message,
+                }
+            })
+            .collect();
+        let mut writer = BufWriter::new(Self::create_file(path, create_dirs)?);
+        serde_json::to_writer_pretty(&mut writer, &records)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Create the output file, reporting a missing parent directory or a
+    /// permission problem with a clear message
+    pub fn create_file(path: &Path, create_dirs: bool) -> Result<File, AnalyzerError> {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+
+        if let Some(parent) = parent
+            && !parent.exists()
+        {
+            if !create_dirs {
+                return Err(AnalyzerError::Output(format!(
+                    "Output directory does not exist: {} (use --create-dirs to create it)",
+                    parent.display()
+                )));
+            }
+            fs::create_dir_all(parent).map_err(|e| {
+                AnalyzerError::Output(format!(
+                    "Failed to create output directory {}: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+
+        File::create(path).map_err(|e| match e.kind() {
+            ErrorKind::PermissionDenied => AnalyzerError::Output(format!(
+                "Permission denied writing output file: {}",
+                path.display()
+            )),
+            _ => AnalyzerError::Io(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::result_of;
+    use std::path::PathBuf;
+
+    /// A fresh directory under the system temp dir, unique to a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wiztree-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn missing_output_dir_is_reported() {
+        let dir = temp_dir("missing-output");
+        let path = dir.join("missing").join("out.json");
+
+        let error = OutputWriter::create_file(&path, false).unwrap_err();
+        assert!(matches!(error, AnalyzerError::Output(_)));
+        assert!(error.to_string().contains("--create-dirs"));
+        assert!(!path.parent().unwrap().exists());
+
+        OutputWriter::create_file(&path, true).unwrap();
+        assert!(path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sizes_are_formatted_with_binary_units() {
+        assert_eq!(format_size(1023), "1023 bytes");
+        assert_eq!(format_size(1536), "1.50 KiB");
+        assert_eq!(format_size(5 << 30), "5.00 GiB");
+        // Labels parse back to the same unit
+        assert_eq!(crate::config::parse_size("1.50 KiB"), Ok(1536));
+    }
+
+    #[test]
+    fn warnings_are_written_as_json_records() {
+        let mut result = result_of(Vec::new());
+        result.add_warning(
+            WarningKind::PermissionDenied,
+            Some(PathBuf::from("/data/secret")),
+            "Cannot access /data/secret".to_string(),
+        );
+        // As loaded from a result saved without details
+        result.warnings.push("Legacy warning".to_string());
+
+        let dir = temp_dir("warnings");
+        let path = dir.join("warnings.json");
+        OutputWriter::write_warnings(&result, &path, false).unwrap();
+        let json: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "kind": "permission_denied",
+                    "path": "/data/secret",
+                    "message": "Cannot access /data/secret",
+                },
+                { "kind": "other", "path": null, "message": "Legacy warning" },
+            ])
+        );
+    }
+
+    #[test]
+    fn sort_orders_entries() {
+        let result = result_of(
+            [("/data/b", 2), ("/data/c", 1), ("/data/a", 2)]
+                .into_iter()
+                .map(|(path, size)| FileEntry {
+                    path: PathBuf::from(path),
+                    size,
+                    depth: 2,
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        // Paths in the order the JSON output lists them
+        let sorted = |order: &str| -> Vec<String> {
+            let mut sorted = result_of(result.entries.clone());
+            order
+                .parse::<SortOrder>()
+                .unwrap()
+                .sort(&mut sorted.entries);
+            let json: serde_json::Value =
+                serde_json::from_str(&JsonFormatter.format(&sorted).unwrap()).unwrap();
+            json["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["path"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(sorted("none"), ["/data/b", "/data/c", "/data/a"]);
+        assert_eq!(sorted("path"), ["/data/a", "/data/b", "/data/c"]);
+        // Ties in size are broken by path
+        assert_eq!(sorted("size-desc"), ["/data/a", "/data/b", "/data/c"]);
+        assert_eq!(sorted("SIZE-ASC"), ["/data/c", "/data/a", "/data/b"]);
+        assert!("size".parse::<SortOrder>().is_err());
+    }
 }
@@ -0,0 +1,220 @@
+// Streaming newline-delimited JSON writer
+
+use crate::analyzer::FileEntry;
+use crate::error::AnalyzerError;
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Controls how often buffered streaming output is flushed
+///
+/// A flush happens once either limit is reached, so downstream readers see
+/// data promptly without paying for a flush on every line. Limits are checked
+/// as entries are written; a [`FlushTimer`] also enforces the interval while
+/// no entries arrive.
+#[derive(Debug, Clone, Copy)]
+pub struct FlushPolicy {
+    /// Flush after this many buffered entries
+    pub max_entries: usize,
+    /// Flush when this much time has passed since the last flush
+    pub max_interval: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            max_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Writes file entries as one JSON object per line while a scan is running
+pub struct NdjsonWriter<W: Write> {
+    writer: BufWriter<W>,
+    policy: FlushPolicy,
+    pending: usize,
+    last_flush: Instant,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(inner: W, policy: FlushPolicy) -> Self {
+        Self {
+            writer: BufWriter::new(inner),
+            policy,
+            pending: 0,
+            last_flush: Instant::now(),
+            error: None,
+        }
+    }
+
+    /// Write one entry, flushing according to the policy
+    ///
+    /// The first write error is kept and reported by [`NdjsonWriter::finish`];
+    /// later entries are dropped.
+    pub fn write_entry(&mut self, entry: &FileEntry) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.try_write_entry(entry) {
+            self.error = Some(e);
+        }
+    }
+
+    fn try_write_entry(&mut self, entry: &FileEntry) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        self.writer.write_all(b"\n")?;
+        self.pending += 1;
+
+        if self.pending >= self.policy.max_entries
+            || self.last_flush.elapsed() >= self.policy.max_interval
+        {
+            self.writer.flush()?;
+            self.pending = 0;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Flush any entries written since the last flush
+    ///
+    /// A failure is kept like a write error.
+    pub fn flush_pending(&mut self) {
+        if self.error.is_some() || self.pending == 0 {
+            return;
+        }
+        match self.writer.flush() {
+            Ok(()) => {
+                self.pending = 0;
+                self.last_flush = Instant::now();
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Flush remaining output and report the first write error, if any
+    pub fn finish(mut self) -> Result<(), AnalyzerError> {
+        if let Some(e) = self.error.take() {
+            return Err(AnalyzerError::Io(e));
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Flushes a shared writer on a background thread every policy interval
+///
+/// Without it, entries found just before a slow directory would sit in the
+/// buffer until the next entry is written. The thread holds a reference to
+/// the writer until the timer is dropped.
+pub struct FlushTimer {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FlushTimer {
+    pub fn start<W: Write + Send + 'static>(writer: Arc<Mutex<NdjsonWriter<W>>>) -> Self {
+        let interval = writer
+            .lock()
+            .unwrap()
+            .policy
+            .max_interval
+            .max(Duration::from_millis(1));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                writer.lock().unwrap().flush_pending();
+            }
+        });
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for FlushTimer {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up to exit
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::entry;
+
+    /// A writer whose contents can be inspected while it is owned elsewhere
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn lines(&self) -> usize {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushes_after_max_entries() {
+        let buffer = SharedBuffer::default();
+        let policy = FlushPolicy {
+            max_entries: 2,
+            max_interval: Duration::from_secs(3600),
+        };
+        let mut writer = NdjsonWriter::new(buffer.clone(), policy);
+
+        writer.write_entry(&entry("a", 1));
+        assert_eq!(buffer.lines(), 0);
+        writer.write_entry(&entry("b", 1));
+        assert_eq!(buffer.lines(), 2);
+        writer.write_entry(&entry("c", 1));
+        writer.finish().unwrap();
+        assert_eq!(buffer.lines(), 3);
+    }
+
+    #[test]
+    fn timer_flushes_within_the_interval() {
+        let buffer = SharedBuffer::default();
+        let policy = FlushPolicy {
+            max_entries: usize::MAX,
+            max_interval: Duration::from_millis(20),
+        };
+        let writer = Arc::new(Mutex::new(NdjsonWriter::new(buffer.clone(), policy)));
+        writer.lock().unwrap().write_entry(&entry("a", 1));
+        assert_eq!(buffer.lines(), 0);
+
+        let timer = FlushTimer::start(writer.clone());
+        let started = Instant::now();
+        while buffer.lines() == 0 && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(buffer.lines(), 1);
+
+        // Dropping the timer releases its reference to the writer
+        drop(timer);
+        assert!(Arc::into_inner(writer).is_some());
+    }
+}
@@ -0,0 +1,55 @@
+// Per-owner size report formatter
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub struct OwnerFormatter;
+
+impl OwnerFormatter {
+    /// Load uid to username mappings from /etc/passwd
+    fn user_names() -> HashMap<u32, String> {
+        let Ok(passwd) = std::fs::read_to_string("/etc/passwd") else {
+            return HashMap::new();
+        };
+
+        passwd
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?;
+                let uid = fields.nth(1)?.parse().ok()?;
+                Some((uid, name.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl OutputFormatter for OwnerFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let names = Self::user_names();
+        let mut owners: Vec<(u32, u64)> = result.size_by_owner().into_iter().collect();
+        owners.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== Size by Owner ===");
+        let _ = writeln!(out);
+        for (uid, size) in owners {
+            match names.get(&uid) {
+                Some(name) => {
+                    let _ = writeln!(out, "  {} ({}): {} bytes", name, uid, size);
+                }
+                None => {
+                    let _ = writeln!(out, "  {}: {} bytes", uid, size);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
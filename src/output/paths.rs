@@ -0,0 +1,97 @@
+// Plain path list formatter for piping into other commands
+
+use crate::analyzer::{AnalysisResult, normalize_separators};
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+
+#[derive(Debug, Clone)]
+pub struct PathListFormatter {
+    /// Terminator written after each path
+    pub separator: char,
+}
+
+impl Default for PathListFormatter {
+    fn default() -> Self {
+        Self { separator: '\n' }
+    }
+}
+
+impl PathListFormatter {
+    /// Newline-terminated paths
+    pub fn lines() -> Self {
+        Self::default()
+    }
+
+    /// NUL-terminated paths, safe for names containing spaces or newlines
+    ///
+    /// Paths are written exactly as found, without normalizing separators,
+    /// so they can be passed back to the filesystem.
+    pub fn nul() -> Self {
+        Self { separator: '\0' }
+    }
+}
+
+impl OutputFormatter for PathListFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+        for entry in &result.entries {
+            if self.separator == '\0' {
+                // Output is a String, so a name that isn't UTF-8 is refused
+                // rather than written with replacement characters
+                let path = entry.path.to_str().ok_or_else(|| {
+                    AnalyzerError::PathError(format!(
+                        "Cannot write non-UTF-8 path with --print0: {}",
+                        entry.path.display()
+                    ))
+                })?;
+                out.push_str(path);
+            } else {
+                out.push_str(&normalize_separators(&entry.path));
+            }
+            out.push(self.separator);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, result_of};
+
+    fn result() -> AnalysisResult {
+        result_of(vec![entry("a.txt", 1), entry("dir/with space.txt", 1)])
+    }
+
+    #[test]
+    fn lines_end_with_newlines() {
+        let out = PathListFormatter::lines().format(&result()).unwrap();
+        assert_eq!(out, "a.txt\ndir/with space.txt\n");
+    }
+
+    #[test]
+    fn nul_terminates_every_path() {
+        let out = PathListFormatter::nul().format(&result()).unwrap();
+        assert_eq!(out, "a.txt\0dir/with space.txt\0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nul_writes_paths_verbatim() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::PathBuf;
+
+        // A backslash is part of a Unix file name, not a separator
+        let result = result_of(vec![entry("dir/back\\slash.txt", 1)]);
+        let out = PathListFormatter::nul().format(&result).unwrap();
+        assert_eq!(out, "dir/back\\slash.txt\0");
+
+        let mut invalid = entry("", 1);
+        invalid.path = PathBuf::from(OsStr::from_bytes(b"dir/caf\xe9.txt"));
+        let error = PathListFormatter::nul()
+            .format(&result_of(vec![invalid]))
+            .unwrap_err();
+        assert!(matches!(error, AnalyzerError::PathError(_)), "{}", error);
+    }
+}
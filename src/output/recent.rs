@@ -0,0 +1,48 @@
+// Report of recently modified directories
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct RecentDirsFormatter {
+    /// Report directories modified within this long before `now`
+    pub within: Duration,
+    pub now: SystemTime,
+}
+
+impl RecentDirsFormatter {
+    pub fn new(within: Duration) -> Self {
+        Self {
+            within,
+            now: SystemTime::now(),
+        }
+    }
+}
+
+impl OutputFormatter for RecentDirsFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let since = self
+            .now
+            .checked_sub(self.within)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let dirs = result.recently_modified_dirs(since);
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            out,
+            "=== Recently Modified Directories ({}, within {}s) ===",
+            dirs.len(),
+            self.within.as_secs()
+        );
+        for dir in dirs {
+            let age = self.now.duration_since(dir.modified).unwrap_or_default();
+            let _ = writeln!(out, "  [{}s ago] {}", age.as_secs(), dir.path.display());
+        }
+
+        Ok(out)
+    }
+}
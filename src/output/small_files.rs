@@ -0,0 +1,31 @@
+// Report of directories holding many files below the minimum size
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormatter;
+use std::fmt::Write;
+
+pub struct SmallFileHotspotFormatter {
+    /// Minimum number of small files for a directory to be reported
+    pub threshold_count: usize,
+}
+
+impl OutputFormatter for SmallFileHotspotFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let hotspots = result.small_file_hotspots(self.threshold_count);
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== Small File Hotspots ({}) ===", hotspots.len());
+        for hotspot in hotspots {
+            let _ = writeln!(
+                out,
+                "  {} small files: {}",
+                hotspot.small_file_count,
+                hotspot.path.display()
+            );
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,79 @@
+// Statistics-only output formatter (size and depth histograms)
+
+use crate::analyzer::{AnalysisResult, size_bucket_range};
+use crate::error::AnalyzerError;
+use crate::output::{OutputFormatter, disk_line, peak_memory_line};
+use std::fmt::Write;
+
+pub struct StatsFormatter;
+
+impl OutputFormatter for StatsFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== File Analysis Statistics ===");
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Total Size: {} bytes", result.total_size);
+        if result.total_allocated_size > 0 {
+            let _ = writeln!(out, "Allocated Size: {} bytes", result.total_allocated_size);
+        }
+        let _ = writeln!(out, "File Count: {}", result.file_count);
+        let _ = writeln!(out, "Directory Count: {}", result.directory_count);
+        let _ = writeln!(out, "Symlink Count: {}", result.symlink_count);
+        for line in [disk_line(result), peak_memory_line(result)]
+            .into_iter()
+            .flatten()
+        {
+            let _ = writeln!(out, "{}", line);
+        }
+        if result.incomplete {
+            let _ = writeln!(out, "Incomplete: true");
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "=== Size Distribution ===");
+        for (bucket, count) in result.size_histogram.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let (low, high) = size_bucket_range(bucket);
+            let _ = writeln!(out, "  [{} - {}) bytes: {}", low, high, count);
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "=== Depth Distribution ===");
+        for (depth, count) in result.depth_histogram.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let _ = writeln!(out, "  Depth {}: {}", depth, count);
+        }
+
+        if !result.mount_crossings.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "=== Mount Crossings ({}) ===",
+                result.mount_crossings.len()
+            );
+            for path in &result.mount_crossings {
+                let _ = writeln!(out, "  {}", path.display());
+            }
+        }
+
+        if !result.truncated_dirs.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "=== Truncated Directories ({}) ===",
+                result.truncated_dirs.len()
+            );
+            for path in &result.truncated_dirs {
+                let _ = writeln!(out, "  {}", path.display());
+            }
+        }
+
+        Ok(out)
+    }
+}
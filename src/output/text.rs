@@ -0,0 +1,167 @@
+// Human-readable text output formatter
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::{OutputFormatter, disk_line, format_size, peak_memory_line};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Default)]
+pub struct TextFormatter {
+    /// Add a bar chart of file counts per depth, with the longest bar this
+    /// many characters wide
+    pub depth_chart_width: Option<usize>,
+}
+
+impl TextFormatter {
+    /// Draw one bar per populated depth, scaled to the largest count
+    fn write_depth_chart(out: &mut String, result: &AnalysisResult, width: usize) {
+        let max = result.depth_histogram.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return;
+        }
+        let label_width = (result.depth_histogram.len() - 1).to_string().len();
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "=== Depth Histogram ===");
+        for (depth, &count) in result.depth_histogram.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            // Populated depths always get at least one character
+            let length = ((count as f64 / max as f64) * width as f64)
+                .round()
+                .max(1.0) as usize;
+            let _ = writeln!(
+                out,
+                "  {:>w$} | {} {}",
+                depth,
+                "#".repeat(length),
+                count,
+                w = label_width
+            );
+        }
+    }
+}
+
+impl OutputFormatter for TextFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "=== File Analysis Results ===");
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "Total Size: {} bytes ({})",
+            result.total_size,
+            format_size(result.total_size)
+        );
+        if result.total_allocated_size > 0 {
+            let _ = writeln!(
+                out,
+                "Allocated Size: {} bytes ({})",
+                result.total_allocated_size,
+                format_size(result.total_allocated_size)
+            );
+        }
+        let _ = writeln!(out, "File Count: {}", result.file_count);
+        let _ = writeln!(out, "Directory Count: {}", result.directory_count);
+        let _ = writeln!(out, "Symlink Count: {}", result.symlink_count);
+        let target_size = result.symlink_target_size();
+        if target_size > 0 {
+            let _ = writeln!(out, "Symlink Target Size: {} bytes", target_size);
+            let _ = writeln!(
+                out,
+                "Reachable Only via Symlinks: {} bytes",
+                result.symlink_only_size()
+            );
+        }
+        for line in [disk_line(result), peak_memory_line(result)]
+            .into_iter()
+            .flatten()
+        {
+            let _ = writeln!(out, "{}", line);
+        }
+        if result.incomplete {
+            let _ = writeln!(out, "Incomplete: true");
+        }
+
+        if let Some(width) = self.depth_chart_width {
+            Self::write_depth_chart(&mut out, result, width);
+        }
+
+        if !result.warnings.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "=== Warnings ({}) ===", result.warnings.len());
+            for warning in &result.warnings {
+                let _ = writeln!(out, "  - {}", warning);
+            }
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "=== Files ({}) ===", result.entries.len());
+        for entry in &result.entries {
+            let _ = write!(
+                out,
+                "  [Depth {}] {} bytes: {}",
+                entry.depth,
+                entry.size,
+                entry.path.display()
+            );
+            if let Some(ref target) = entry.target {
+                let _ = write!(out, " -> {}", target.display());
+            }
+            if let Some(ref real_path) = entry.real_path {
+                let _ = write!(out, " [real: {}]", real_path.display());
+            }
+            if let Some(ref content_type) = entry.content_type {
+                let _ = write!(out, " [{}]", content_type);
+            }
+            if let Some(target_size) = entry.target_size {
+                let _ = write!(out, " ({} bytes)", target_size);
+            }
+            let _ = writeln!(out);
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, result_of};
+
+    #[test]
+    fn depth_chart_has_one_proportional_bar_per_depth() {
+        let result = result_of(
+            [
+                "/data/a",
+                "/data/b",
+                "/data/c",
+                "/data/d",
+                "/data/x/e",
+                "/data/x/f",
+                "/data/x/y/z/g",
+            ]
+            .into_iter()
+            .map(|path| entry(path, 1))
+            .collect(),
+        );
+        let formatter = TextFormatter {
+            depth_chart_width: Some(8),
+        };
+        let text = formatter.format(&result).unwrap();
+
+        let chart: Vec<&str> = text
+            .lines()
+            .skip_while(|line| *line != "=== Depth Histogram ===")
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .collect();
+        assert_eq!(chart, ["  2 | ######## 4", "  3 | #### 2", "  5 | ## 1"]);
+
+        let plain = TextFormatter::default().format(&result).unwrap();
+        assert!(!plain.contains("Depth Histogram"));
+    }
+}
@@ -0,0 +1,104 @@
+// Shareable URL formatter embedding a gzipped, base64-encoded metafile
+
+use crate::analyzer::AnalysisResult;
+use crate::error::AnalyzerError;
+use crate::output::{MetafileFormatter, OutputFormatter};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::io::Write;
+
+/// Prefix producing a data URL that browsers can download directly
+pub const DATA_URL_PREFIX: &str = "data:application/gzip;base64,";
+
+/// Practical URL length limit of common browsers (2 MiB)
+const MAX_URL_LENGTH: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct AnalyzeUrlFormatter {
+    pub metafile: MetafileFormatter,
+    /// Text placed before the base64 payload, e.g. a viewer URL ending in `#`
+    pub prefix: String,
+}
+
+impl Default for AnalyzeUrlFormatter {
+    fn default() -> Self {
+        Self {
+            metafile: MetafileFormatter::default(),
+            prefix: DATA_URL_PREFIX.to_string(),
+        }
+    }
+}
+
+impl OutputFormatter for AnalyzeUrlFormatter {
+    fn format(&self, result: &AnalysisResult) -> Result<String, AnalyzerError> {
+        let json = self.metafile.format(result)?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(json.as_bytes())?;
+        let compressed = encoder.finish()?;
+
+        let url = format!("{}{}", self.prefix, STANDARD.encode(compressed));
+        if url.len() > MAX_URL_LENGTH {
+            eprintln!(
+                "Warning: URL is too long ({} bytes, {} MB)",
+                url.len(),
+                url.len() >> 20
+            );
+            eprintln!("Most browsers reject URLs longer than 2 MB");
+            eprintln!("Consider writing a metafile with --output instead");
+        }
+
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, result_of};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    /// Parse a metafile, ordering each input's imports by path since the
+    /// tree is built from a hash map
+    fn sorted_imports(json: &str) -> serde_json::Value {
+        let mut value: serde_json::Value = serde_json::from_str(json).unwrap();
+        for input in value["inputs"].as_object_mut().unwrap().values_mut() {
+            if let Some(imports) = input["imports"].as_array_mut() {
+                imports.sort_by_key(|import| import["path"].to_string());
+            }
+        }
+        value
+    }
+
+    #[test]
+    fn url_decodes_to_the_metafile() {
+        let result = result_of(vec![entry("/data/a.txt", 10), entry("/data/src/b.rs", 20)]);
+        let formatter = AnalyzeUrlFormatter {
+            prefix: "https://esbuild.github.io/analyze/#".to_string(),
+            ..Default::default()
+        };
+
+        let url = formatter.format(&result).unwrap();
+        let payload = url
+            .strip_prefix("https://esbuild.github.io/analyze/#")
+            .unwrap();
+        let mut json = String::new();
+        GzDecoder::new(STANDARD.decode(payload).unwrap().as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+
+        let expected = formatter.metafile.format(&result).unwrap();
+        assert_eq!(sorted_imports(&json), sorted_imports(&expected));
+    }
+
+    #[test]
+    fn default_prefix_is_a_data_url() {
+        let url = AnalyzeUrlFormatter::default()
+            .format(&result_of(Vec::new()))
+            .unwrap();
+        assert!(url.starts_with(DATA_URL_PREFIX));
+    }
+}
@@ -1,34 +1,70 @@
 // File processing and size calculation
 
-use crate::analyzer::FileEntry;
-use crate::config::AnalyzerConfig;
+use crate::analyzer::{FileEntry, lexical_absolute};
+use crate::collector::SmallFileCounts;
+use crate::config::{AnalyzerConfig, SizeMode};
+use crate::disk;
 use crate::error::AnalyzerError;
+use crate::filesystem::FileSystem;
 use crate::link_handler::LinkHandler;
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Number of leading bytes read to sniff a content type, as much as `infer`
+/// inspects
+#[cfg(feature = "content-type")]
+const SNIFF_LEN: usize = 8192;
+
 pub struct FileProcessor {
     config: Arc<AnalyzerConfig>,
     link_handler: Arc<LinkHandler>,
+    fs: Arc<dyn FileSystem>,
+    cluster_size: Option<u64>,
+    small_file_counts: Option<SmallFileCounts>,
 }
 
 impl FileProcessor {
-    pub fn new(config: Arc<AnalyzerConfig>, link_handler: Arc<LinkHandler>) -> Self {
+    pub fn new(
+        config: Arc<AnalyzerConfig>,
+        link_handler: Arc<LinkHandler>,
+        fs: Arc<dyn FileSystem>,
+    ) -> Self {
+        // Query the cluster size once up front rather than per file
+        let cluster_size = if config.size_mode == SizeMode::Allocated && config.round_to_cluster {
+            disk::cluster_size(&config.root_path)
+        } else {
+            None
+        };
+
         Self {
             config,
             link_handler,
+            fs,
+            cluster_size,
+            small_file_counts: None,
         }
     }
 
+    /// Count files below the minimum size per directory when
+    /// [`AnalyzerConfig::count_small_files`] is set, even though they are left
+    /// out of the results
+    pub fn set_small_file_counts(&mut self, counts: SmallFileCounts) {
+        self.small_file_counts = Some(counts);
+    }
+
     /// Process a file and return a FileEntry if it should be included
     pub fn process_file(
         &self,
         path: &Path,
         depth: usize,
     ) -> Result<Option<FileEntry>, AnalyzerError> {
+        // Apply include filter
+        if !self.config.should_include_file(path) {
+            return Ok(None);
+        }
+
         // Get metadata (follow symlinks for size)
-        let symlink_metadata = fs::symlink_metadata(path)?;
+        let symlink_metadata = self.fs.symlink_metadata(path)?;
         let is_symlink = symlink_metadata.is_symlink();
 
         // For symlinks, check if it's a duplicate
@@ -37,24 +73,75 @@ impl FileProcessor {
             return Ok(None);
         }
 
+        // Symlinks are taken as they are when they are never resolved
+        let resolve = !(is_symlink && self.config.no_symlinks);
+
         // Get the actual file metadata (following symlinks)
-        let metadata = match fs::metadata(path) {
-            Ok(m) => m,
-            Err(_) => {
-                // Broken symlink or inaccessible file
-                return Ok(None);
+        let metadata = if resolve {
+            match self.fs.metadata(path) {
+                Ok(m) => m,
+                Err(_) => {
+                    // Broken symlink or inaccessible file
+                    return Ok(None);
+                }
             }
+        } else {
+            symlink_metadata.clone()
         };
 
-        // Only process regular files
-        if !metadata.is_file() {
+        // Only process regular files (and unresolved symlinks)
+        if !metadata.is_file() && resolve {
             return Ok(None);
         }
 
-        let size = metadata.len();
+        // Follow Windows shortcuts, taking the size from the target when available
+        let shortcut = self.resolve_shortcut(path);
+        let size = shortcut
+            .as_ref()
+            .and_then(|(_, size)| *size)
+            .unwrap_or(metadata.len())
+            + self.alternate_streams_size(path);
+
+        // Keep the target's size apart from the link's own size, which is the
+        // size recorded and filtered on
+        let (size, target_size, sized) =
+            if is_symlink && resolve && self.config.symlink_target_sizes {
+                (symlink_metadata.len(), Some(size), &symlink_metadata)
+            } else {
+                (size, None, &metadata)
+            };
 
-        // Apply size filter
-        if !self.should_include(size) {
+        // Apply size filter; files below the minimum size are still counted
+        // per directory when asked, once they pass the other filters
+        let small = self.config.count_small_files
+            && self.small_file_counts.is_some()
+            && size < self.config.min_file_size;
+        if !small && !self.should_include(size) {
+            return Ok(None);
+        }
+
+        // Apply owner filter
+        if !self
+            .config
+            .owner_matches(metadata.owner.map(|(file_uid, _)| file_uid))
+        {
+            return Ok(None);
+        }
+
+        // Apply modification time filter
+        if !self.config.modified_matches(metadata.modified) {
+            return Ok(None);
+        }
+
+        // Sniff the content type, reading the file only when asked
+        let content_type = if resolve
+            && (self.config.sniff_content_type || self.config.content_type_filter.is_some())
+        {
+            self.content_type(path)
+        } else {
+            None
+        };
+        if !self.config.content_type_matches(content_type.as_deref()) {
             return Ok(None);
         }
 
@@ -64,24 +151,364 @@ impl FileProcessor {
             return Ok(None);
         }
 
+        // Skip symlinks whose target was already counted
+        if is_symlink
+            && resolve
+            && self.config.follow_symlinks_once
+            && self.link_handler.is_duplicate_target(path, &metadata)
+        {
+            return Ok(None);
+        }
+
+        // Count a small file only now, so filtered files and extra links to
+        // one inode are left out
+        if small {
+            if let Some(ref counts) = self.small_file_counts
+                && let Some(parent) = path.parent()
+            {
+                *counts
+                    .lock()
+                    .unwrap()
+                    .entry(parent.to_path_buf())
+                    .or_default() += 1;
+            }
+            return Ok(None);
+        }
+
         // Resolve symlink target if applicable
-        let target = if is_symlink {
+        let target = if !resolve {
+            None
+        } else if is_symlink {
             self.link_handler.resolve_link(path).ok()
+        } else {
+            shortcut.map(|(target, _)| target)
+        };
+
+        // Every entry gets an allocated size in allocated mode, falling back to
+        // the logical size, so total_allocated_size covers all of them
+        let allocated_size = match self.config.size_mode {
+            SizeMode::Logical => None,
+            SizeMode::Allocated => sized
+                .allocated_size
+                .or_else(|| {
+                    self.cluster_size
+                        .map(|c| disk::round_up_to_cluster(size, c))
+                })
+                .or(Some(size)),
+        };
+
+        // Canonicalizing is costly, so only when asked, and never when
+        // nothing is resolved
+        let real_path = if self.config.record_real_path && !self.config.no_symlinks {
+            self.fs
+                .canonicalize(path)
+                .ok()
+                .filter(|real| *real != lexical_absolute(path))
         } else {
             None
         };
 
+        let (uid, gid) = match metadata.owner {
+            Some((uid, gid)) if self.config.capture_owner => (Some(uid), Some(gid)),
+            _ => (None, None),
+        };
+
         Ok(Some(FileEntry {
             path: path.to_path_buf(),
             size,
             depth,
             is_symlink,
             target,
+            allocated_size,
+            uid,
+            gid,
+            target_size,
+            real_path,
+            modified: metadata.modified.filter(|_| self.config.record_file_mtimes),
+            content_type,
         }))
     }
 
-    /// Check if a file should be included based on size filter
+    /// Resolve a Windows shortcut to its target path and the target's size
+    #[cfg(all(windows, feature = "lnk"))]
+    fn resolve_shortcut(&self, path: &Path) -> Option<(PathBuf, Option<u64>)> {
+        if !self.config.follow_lnk || !LinkHandler::is_shortcut(path) {
+            return None;
+        }
+
+        let target = self.link_handler.resolve_shortcut(path).ok()?;
+        let size = self
+            .fs
+            .metadata(&target)
+            .ok()
+            .filter(|m| m.is_file())
+            .map(|m| m.len());
+        Some((target, size))
+    }
+
+    #[cfg(not(all(windows, feature = "lnk")))]
+    fn resolve_shortcut(&self, _path: &Path) -> Option<(PathBuf, Option<u64>)> {
+        None
+    }
+
+    /// Detect a file's MIME type from its leading bytes
+    #[cfg(feature = "content-type")]
+    fn content_type(&self, path: &Path) -> Option<String> {
+        let head = self.fs.read_head(path, SNIFF_LEN).ok()?;
+        infer::get(&head).map(|kind| kind.mime_type().to_string())
+    }
+
+    #[cfg(not(feature = "content-type"))]
+    fn content_type(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
+    /// Get the total size of a file's alternate data streams, if enabled
+    #[cfg(all(windows, feature = "ads"))]
+    fn alternate_streams_size(&self, path: &Path) -> u64 {
+        if !self.config.include_ads {
+            return 0;
+        }
+        disk::alternate_streams_size(path).unwrap_or(0)
+    }
+
+    #[cfg(not(all(windows, feature = "ads")))]
+    fn alternate_streams_size(&self, _path: &Path) -> u64 {
+        0
+    }
+
+    /// Check if a file should be included based on size filters
     pub fn should_include(&self, size: u64) -> bool {
-        size >= self.config.min_file_size
+        self.config.size_matches(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(windows, any(feature = "lnk", feature = "ads")))]
+    use crate::FileAnalyzer;
+    use crate::test_support::scan;
+    use crate::{AnalysisResult, AnalyzerConfig, MemoryFileSystem};
+    use std::path::{Path, PathBuf};
+
+    fn entry<'a>(result: &'a AnalysisResult, path: &str) -> &'a crate::FileEntry {
+        result
+            .entries
+            .iter()
+            .find(|e| e.path == Path::new(path))
+            .unwrap()
+    }
+
+    fn linked_tree() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/a.txt", 10)
+            .add_file("/outside/big.bin", 1000)
+            .add_symlink("/data/big", "/outside/big.bin")
+            .add_symlink("/data/a-link", "a.txt");
+        fs
+    }
+
+    #[test]
+    fn symlink_target_sizes_are_kept_apart() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.symlink_target_sizes = true;
+        let result = scan(config, linked_tree());
+
+        let big = entry(&result, "/data/big");
+        // The link's own size is the length of its target path
+        assert_eq!(big.size, "/outside/big.bin".len() as u64);
+        assert_eq!(big.target_size, Some(1000));
+        assert_eq!(entry(&result, "/data/a-link").target_size, Some(10));
+        assert_eq!(result.symlink_target_size(), 1010);
+        // a.txt was scanned directly, big.bin only through its link
+        assert_eq!(result.symlink_only_size(), 1000);
+    }
+
+    #[test]
+    fn size_filter_applies_to_the_recorded_size() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.symlink_target_sizes = true;
+        config.min_file_size = 100;
+        let result = scan(config, linked_tree());
+
+        // Links are recorded at their own small size, so none pass
+        assert!(result.entries.iter().all(|e| !e.is_symlink));
+    }
+
+    #[test]
+    fn symlinks_take_their_target_size_by_default() {
+        let result = scan(AnalyzerConfig::new(PathBuf::from("/data")), linked_tree());
+
+        let big = entry(&result, "/data/big");
+        assert_eq!(big.size, 1000);
+        assert_eq!(big.target_size, None);
+        assert_eq!(result.symlink_target_size(), 0);
+    }
+
+    /// Build a minimal shell link whose LinkInfo holds `target` as the local base path
+    #[cfg(all(windows, feature = "lnk"))]
+    fn shortcut_to(target: &str) -> Vec<u8> {
+        const LINK_INFO_HEADER_SIZE: u32 = 0x1c;
+        const VOLUME_ID: &[u8] = &[
+            0x11, 0, 0, 0, // VolumeIDSize
+            3, 0, 0, 0, // DriveType: fixed
+            0, 0, 0, 0, // DriveSerialNumber
+            0x10, 0, 0, 0, // VolumeLabelOffset
+            0, // empty label
+        ];
+        let base_path: Vec<u8> = target.bytes().chain([0]).collect();
+        let base_path_offset = LINK_INFO_HEADER_SIZE + VOLUME_ID.len() as u32;
+        let suffix_offset = base_path_offset + base_path.len() as u32;
+        let link_info_size = suffix_offset + 1;
+
+        let mut lnk = Vec::new();
+        // ShellLinkHeader: size, CLSID, LinkFlags (HasLinkInfo), attributes
+        lnk.extend(0x4cu32.to_le_bytes());
+        lnk.extend([
+            0x01, 0x14, 0x02, 0, 0, 0, 0, 0, 0xc0, 0, 0, 0, 0, 0, 0, 0x46,
+        ]);
+        lnk.extend(0x02u32.to_le_bytes());
+        lnk.extend(0x20u32.to_le_bytes());
+        // Times, file size, icon index
+        lnk.extend([0; 8 * 3 + 4 + 4]);
+        // ShowCommand (normal), hot key and reserved fields
+        lnk.extend(1u32.to_le_bytes());
+        lnk.extend([0; 2 + 2 + 4 + 4]);
+
+        // LinkInfo with a VolumeID and LocalBasePath
+        for field in [
+            link_info_size,
+            LINK_INFO_HEADER_SIZE,
+            1, // VolumeIDAndLocalBasePath
+            LINK_INFO_HEADER_SIZE,
+            base_path_offset,
+            0,
+            suffix_offset,
+        ] {
+            lnk.extend(field.to_le_bytes());
+        }
+        lnk.extend(VOLUME_ID);
+        lnk.extend(base_path);
+        lnk.push(0); // empty CommonPathSuffix
+
+        // TerminalBlock
+        lnk.extend(0u32.to_le_bytes());
+        lnk
+    }
+
+    #[cfg(all(windows, feature = "lnk"))]
+    #[test]
+    fn followed_shortcuts_record_their_target() {
+        let dir = std::env::temp_dir().join(format!("wiztree-lnk-{}", std::process::id()));
+        let target = dir.join("target").join("target.txt");
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(dir.join("links")).unwrap();
+        std::fs::write(&target, [0u8; 100]).unwrap();
+        let shortcut = shortcut_to(target.to_str().unwrap());
+        std::fs::write(dir.join("links").join("target.lnk"), &shortcut).unwrap();
+
+        let scan = |follow_lnk| {
+            let mut config = AnalyzerConfig::new(dir.join("links"));
+            config.follow_lnk = follow_lnk;
+            FileAnalyzer::new(config).analyze()
+        };
+        let (followed, plain) = (scan(true), scan(false));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let followed = followed.unwrap();
+        assert_eq!(followed.entries.len(), 1);
+        assert_eq!(
+            followed.entries[0].target.as_deref(),
+            Some(target.as_path())
+        );
+        assert_eq!(followed.entries[0].size, 100);
+        let plain = plain.unwrap();
+        assert_eq!(plain.entries[0].target, None);
+        assert_eq!(plain.entries[0].size, shortcut.len() as u64);
+    }
+
+    #[cfg(all(windows, feature = "ads"))]
+    #[test]
+    fn alternate_streams_count_toward_the_size() {
+        let dir = std::env::temp_dir().join(format!("wiztree-ads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), [0u8; 100]).unwrap();
+        std::fs::write(dir.join("file.txt:extra"), [0u8; 40]).unwrap();
+
+        let scan = |include_ads| {
+            let mut config = AnalyzerConfig::new(dir.clone());
+            config.include_ads = include_ads;
+            FileAnalyzer::new(config).analyze()
+        };
+        let (with_ads, without_ads) = (scan(true), scan(false));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let with_ads = with_ads.unwrap();
+        assert_eq!(with_ads.entries.len(), 1);
+        assert_eq!(with_ads.entries[0].size, 140);
+        assert_eq!(with_ads.total_size, 140);
+        assert_eq!(without_ads.unwrap().entries[0].size, 100);
+    }
+
+    #[test]
+    fn real_paths_are_recorded_for_symlinks() {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.record_real_path = true;
+        let result = scan(config, linked_tree());
+
+        assert_eq!(
+            entry(&result, "/data/big").real_path.as_deref(),
+            Some(Path::new("/outside/big.bin"))
+        );
+        assert_eq!(
+            entry(&result, "/data/a-link").real_path.as_deref(),
+            Some(Path::new("/data/a.txt"))
+        );
+        // Entries reached directly have none
+        assert_eq!(entry(&result, "/data/a.txt").real_path, None);
+    }
+
+    #[test]
+    fn follow_symlinks_once_counts_each_target_once() {
+        let mut fs = linked_tree();
+        fs.add_symlink("/data/big-again", "/outside/big.bin")
+            .add_symlink("/data/sub/big-relative", "../../outside/big.bin");
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.follow_symlinks_once = true;
+        let result = scan(config.clone(), fs.clone());
+
+        // a.txt is reached directly and through a-link, big.bin through three links
+        assert_eq!(result.total_size, 10 + 1000);
+        assert_eq!(result.file_count, 2);
+
+        config.follow_symlinks_once = false;
+        assert_eq!(scan(config, fs).total_size, 10 * 2 + 1000 * 3);
+    }
+
+    #[cfg(feature = "content-type")]
+    #[test]
+    fn content_type_is_sniffed_regardless_of_extension() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file_with_contents("/data/image.txt", *b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")
+            .add_file_with_contents("/data/notes.txt", *b"plain text")
+            .add_symlink("/data/link.png", "image.txt");
+
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.content_type_filter = Some("image/*".to_string());
+        let result = scan(config, fs);
+
+        let mut sniffed: Vec<(&Path, Option<&str>)> = result
+            .entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.content_type.as_deref()))
+            .collect();
+        sniffed.sort();
+        assert_eq!(
+            sniffed,
+            [
+                (Path::new("/data/image.txt"), Some("image/png")),
+                (Path::new("/data/link.png"), Some("image/png")),
+            ]
+        );
     }
 }
@@ -0,0 +1,152 @@
+// Named option presets for common tasks
+
+use crate::config::SizeMode;
+
+/// Media file extensions matched by the `media` profile
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "raw", "mp3", "flac", "wav", "ogg", "m4a", "mp4",
+    "mkv", "avi", "mov", "webm",
+];
+
+/// A named bundle of options
+///
+/// Presets are applied first; options given explicitly override them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Source trees: skip VCS metadata, dependencies, build output and
+    /// anything matched by `.gitignore` files
+    Source,
+    /// Photo, audio and video collections: only count media files
+    Media,
+    /// Backup sizing: skip caches and trash, report on-disk sizes
+    Backup,
+}
+
+/// Options set by a profile; `None` and empty lists leave the default
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileOptions {
+    pub ignore_patterns: Vec<String>,
+    pub include_patterns: Vec<String>,
+    pub size_mode: Option<SizeMode>,
+    /// Skip paths matched by `.gitignore` files
+    pub respect_gitignore: bool,
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "source" => Ok(Profile::Source),
+            "media" => Ok(Profile::Media),
+            "backup" => Ok(Profile::Backup),
+            _ => Err(format!("Invalid profile: {}", s)),
+        }
+    }
+}
+
+impl Profile {
+    /// Get the options this profile sets
+    pub fn options(self) -> ProfileOptions {
+        match self {
+            Profile::Source => source(),
+            Profile::Media => media(),
+            Profile::Backup => backup(),
+        }
+    }
+}
+
+fn patterns(patterns: &[&str]) -> Vec<String> {
+    patterns.iter().map(|p| p.to_string()).collect()
+}
+
+fn source() -> ProfileOptions {
+    ProfileOptions {
+        ignore_patterns: patterns(&[
+            "**/.git/**",
+            "**/.hg/**",
+            "**/.svn/**",
+            "**/node_modules/**",
+            "**/target/**",
+            "**/__pycache__/**",
+        ]),
+        respect_gitignore: true,
+        ..Default::default()
+    }
+}
+
+fn media() -> ProfileOptions {
+    ProfileOptions {
+        include_patterns: vec![format!("**/*.{{{}}}", MEDIA_EXTENSIONS.join(","))],
+        ..Default::default()
+    }
+}
+
+fn backup() -> ProfileOptions {
+    ProfileOptions {
+        ignore_patterns: patterns(&[
+            "**/.cache/**",
+            "**/.Trash/**",
+            "**/.Trash-*/**",
+            "**/$RECYCLE.BIN/**",
+            "**/*.tmp",
+        ]),
+        size_mode: Some(SizeMode::Allocated),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnalysisResult, AnalyzerConfig, MemoryFileSystem, test_support};
+    use std::path::PathBuf;
+
+    fn scan(profile: Profile) -> AnalysisResult {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/src/main.rs", 1)
+            .add_file("/data/.git/HEAD", 2)
+            .add_file("/data/node_modules/x/index.js", 4)
+            .add_file("/data/photos/a.jpg", 8)
+            .add_file("/data/music/b.flac", 16)
+            .add_file("/data/.cache/c.bin", 32)
+            .add_file("/data/d.tmp", 64)
+            .add_file_with_contents("/data/.gitignore", "/d.tmp\n");
+
+        let options = profile.options();
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.set_ignore_patterns(options.ignore_patterns).unwrap();
+        config
+            .set_include_patterns(options.include_patterns)
+            .unwrap();
+        if let Some(size_mode) = options.size_mode {
+            config.size_mode = size_mode;
+        }
+        config.respect_gitignore = options.respect_gitignore;
+        test_support::scan(config, fs)
+    }
+
+    #[test]
+    fn source_skips_vcs_dependencies_and_gitignored_files() {
+        // The 7-byte .gitignore itself is counted
+        assert_eq!(scan(Profile::Source).total_size, 1 + 8 + 16 + 32 + 7);
+    }
+
+    #[test]
+    fn media_counts_only_media_files() {
+        assert_eq!(scan(Profile::Media).total_size, 8 + 16);
+    }
+
+    #[test]
+    fn backup_skips_caches_and_reports_allocated_sizes() {
+        let result = scan(Profile::Backup);
+        assert_eq!(result.total_size, 1 + 2 + 4 + 8 + 16 + 7);
+        assert_eq!(result.total_allocated_size, result.total_size);
+    }
+
+    #[test]
+    fn names_parse_case_insensitively() {
+        assert_eq!("Media".parse::<Profile>(), Ok(Profile::Media));
+        assert!("gitignore".parse::<Profile>().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// Incremental aggregation of streamed entries
+
+use crate::analyzer::{FileEntry, size_bucket};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Running size and file count of a group of entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupTotals {
+    pub total_size: u64,
+    pub file_count: usize,
+}
+
+impl RollupTotals {
+    fn add(&mut self, size: u64) {
+        self.total_size += size;
+        self.file_count += 1;
+    }
+}
+
+/// Accumulates per-directory and per-extension totals from entries fed one
+/// at a time, without retaining the entries themselves
+///
+/// Feed it from an entry sink to show live aggregates while a scan runs.
+/// Memory grows with the number of directories and extensions, not files.
+#[derive(Debug, Clone, Default)]
+pub struct RollupAccumulator {
+    root: Option<PathBuf>,
+    total: RollupTotals,
+    directories: BTreeMap<PathBuf, RollupTotals>,
+    extensions: BTreeMap<String, RollupTotals>,
+    size_histogram: Vec<usize>,
+}
+
+impl RollupAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only roll sizes up into directories at or below `root`
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Add an entry to the running totals
+    pub fn add(&mut self, entry: &FileEntry) {
+        self.total.add(entry.size);
+
+        let bucket = size_bucket(entry.size);
+        if self.size_histogram.len() <= bucket {
+            self.size_histogram.resize(bucket + 1, 0);
+        }
+        self.size_histogram[bucket] += 1;
+
+        self.extensions
+            .entry(Self::extension_of(&entry.path))
+            .or_default()
+            .add(entry.size);
+
+        for dir in entry.path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            if let Some(ref root) = self.root
+                && !dir.starts_with(root)
+            {
+                break;
+            }
+            self.directories
+                .entry(dir.to_path_buf())
+                .or_default()
+                .add(entry.size);
+        }
+    }
+
+    /// Totals over every entry seen so far
+    pub fn total(&self) -> RollupTotals {
+        self.total
+    }
+
+    /// Totals of everything under a directory seen so far
+    pub fn directory(&self, path: &Path) -> Option<RollupTotals> {
+        self.directories.get(path).copied()
+    }
+
+    /// All directories with their totals, sorted by path
+    pub fn directories(&self) -> impl Iterator<Item = (&Path, RollupTotals)> {
+        self.directories.iter().map(|(p, t)| (p.as_path(), *t))
+    }
+
+    /// Totals of files with an extension (lowercase, without the dot; empty
+    /// for files without one)
+    pub fn extension(&self, extension: &str) -> Option<RollupTotals> {
+        self.extensions.get(&extension.to_lowercase()).copied()
+    }
+
+    /// All extensions with their totals, sorted by extension
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, RollupTotals)> {
+        self.extensions.iter().map(|(e, t)| (e.as_str(), *t))
+    }
+
+    /// File counts per power-of-two size bucket, as in `AnalysisResult`
+    pub fn size_histogram(&self) -> &[usize] {
+        &self.size_histogram
+    }
+
+    fn extension_of(path: &Path) -> String {
+        path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnalyzerConfig, FileAnalyzer, MemoryFileSystem};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn streamed_rollup_matches_the_result() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/a.TXT", 1)
+            .add_file("/data/src/b.rs", 10)
+            .add_file("/data/src/lib/c.rs", 100)
+            .add_file("/data/src/Makefile", 1000);
+
+        let rollup = Arc::new(Mutex::new(RollupAccumulator::with_root("/data")));
+        let sink = rollup.clone();
+        let mut analyzer = FileAnalyzer::with_file_system(
+            AnalyzerConfig::new(PathBuf::from("/data")),
+            Arc::new(fs),
+        );
+        analyzer.set_entry_sink(Arc::new(move |entry| sink.lock().unwrap().add(entry)));
+        let result = analyzer.analyze().unwrap();
+        let rollup = rollup.lock().unwrap();
+
+        assert_eq!(rollup.total().total_size, result.total_size);
+        assert_eq!(rollup.total().file_count, result.file_count);
+        assert_eq!(rollup.size_histogram(), result.size_histogram);
+        assert_eq!(
+            rollup.directory(Path::new("/data/src")),
+            Some(RollupTotals {
+                total_size: 1110,
+                file_count: 3
+            })
+        );
+        assert_eq!(rollup.extension("rs").map(|t| t.total_size), Some(110));
+        assert_eq!(rollup.extension("txt").map(|t| t.file_count), Some(1));
+        assert_eq!(rollup.extension("").map(|t| t.total_size), Some(1000));
+        // Nothing is rolled up above the root
+        assert_eq!(rollup.directory(Path::new("/")), None);
+    }
+}
@@ -0,0 +1,86 @@
+// Fixtures shared by unit tests
+
+use crate::analyzer::{AnalysisResult, FileAnalyzer, FileEntry};
+use crate::config::AnalyzerConfig;
+use crate::filesystem::{FileMetadata, FileSystem, MemoryFileSystem};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A file entry of `size` bytes, one level deeper per `/` in `path`
+pub(crate) fn entry(path: &str, size: u64) -> FileEntry {
+    FileEntry {
+        path: PathBuf::from(path),
+        size,
+        depth: path.matches('/').count(),
+        ..Default::default()
+    }
+}
+
+/// A result holding `entries`, as if a scan had collected them
+pub(crate) fn result_of(entries: Vec<FileEntry>) -> AnalysisResult {
+    AnalysisResult::from_entries(entries)
+}
+
+/// Analyze a filesystem, failing the test on errors
+pub(crate) fn scan(config: AnalyzerConfig, fs: impl FileSystem + 'static) -> AnalysisResult {
+    FileAnalyzer::with_file_system(config, Arc::new(fs))
+        .analyze()
+        .unwrap()
+}
+
+/// Overrides applied by [`WrappedFileSystem`] on top of its in-memory tree
+pub(crate) trait FileSystemHooks: Send + Sync {
+    /// Adjust the metadata reported for `path`
+    fn remap(&self, _inner: &MemoryFileSystem, _path: &Path, _metadata: &mut FileMetadata) {}
+
+    /// Resolve `path`, by default through the in-memory tree
+    fn canonicalize(&self, inner: &MemoryFileSystem, path: &Path) -> io::Result<PathBuf> {
+        inner.canonicalize(path)
+    }
+}
+
+/// A [`MemoryFileSystem`] with hooks, for faking what the in-memory tree
+/// can't express such as mount points or aliased inodes
+#[derive(Default)]
+pub(crate) struct WrappedFileSystem<H> {
+    pub(crate) inner: MemoryFileSystem,
+    pub(crate) hooks: H,
+}
+
+impl<H: FileSystemHooks> WrappedFileSystem<H> {
+    fn remapped(&self, path: &Path, mut metadata: FileMetadata) -> FileMetadata {
+        self.hooks.remap(&self.inner, path, &mut metadata);
+        metadata
+    }
+}
+
+impl<H: FileSystemHooks> FileSystem for WrappedFileSystem<H> {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileMetadata)>> {
+        Ok(self
+            .inner
+            .read_dir(path)?
+            .into_iter()
+            .map(|(path, metadata)| {
+                let metadata = self.remapped(&path, metadata);
+                (path, metadata)
+            })
+            .collect())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        Ok(self.remapped(path, self.inner.symlink_metadata(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        Ok(self.remapped(path, self.inner.metadata(path)?))
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.hooks.canonicalize(&self.inner, path)
+    }
+}
@@ -1,18 +1,208 @@
 // Traversal strategy trait and implementations
 
+use crate::analyzer::WarningKind;
 use crate::collector::ResultCollector;
 use crate::config::AnalyzerConfig;
 use crate::error::AnalyzerError;
+use crate::gitignore::GitignoreFilter;
 use crate::link_handler::LinkHandler;
-use crate::walker::DirectoryWalker;
+use crate::processor::FileProcessor;
+use crate::walker::{DirEntry, DirectoryWalker};
 use std::path::Path;
 use std::sync::Arc;
 
 pub mod breadth_first;
 pub mod depth_first;
+pub mod parallel;
 
 pub use breadth_first::BreadthFirstTraversal;
 pub use depth_first::DepthFirstTraversal;
+pub use parallel::ParallelTraversal;
+
+/// Check the file count and total size limits, marking the result
+/// incomplete when one is reached
+pub(crate) fn limit_reached(config: &AnalyzerConfig, collector: &ResultCollector) -> bool {
+    let reached = config
+        .max_files
+        .is_some_and(|max_files| collector.file_count() >= max_files)
+        || config
+            .max_total_size
+            .is_some_and(|max_size| collector.total_size() >= max_size);
+    if reached {
+        collector.set_incomplete(true);
+    }
+    reached
+}
+
+/// Everything a strategy needs to visit paths of one root
+pub(crate) struct TraversalContext<'a> {
+    config: &'a AnalyzerConfig,
+    walker: &'a DirectoryWalker,
+    link_handler: &'a Arc<LinkHandler>,
+    collector: &'a ResultCollector,
+    processor: FileProcessor,
+    gitignore: Option<GitignoreFilter>,
+}
+
+impl<'a> TraversalContext<'a> {
+    pub(crate) fn new(
+        root: &Path,
+        config: &'a AnalyzerConfig,
+        walker: &'a DirectoryWalker,
+        link_handler: &'a Arc<LinkHandler>,
+        collector: &'a ResultCollector,
+    ) -> Self {
+        let mut processor = FileProcessor::new(
+            Arc::new(config.clone()),
+            link_handler.clone(),
+            walker.file_system(),
+        );
+        processor.set_small_file_counts(collector.small_file_counts());
+        let gitignore = config
+            .respect_gitignore
+            .then(|| GitignoreFilter::new(root, walker.file_system()));
+        Self {
+            config,
+            walker,
+            link_handler,
+            collector,
+            processor,
+            gitignore,
+        }
+    }
+
+    /// Check the file count and total size limits
+    pub(crate) fn limit_reached(&self) -> bool {
+        limit_reached(self.config, self.collector)
+    }
+
+    /// Visit one path and return the entries to traverse next
+    ///
+    /// Skips ignored paths and cycles, records a directory or counts a file,
+    /// and lists a directory's children. Problems are recorded as warnings;
+    /// only cancellation and processing errors are returned. Strategies
+    /// differ only in how they schedule the returned children.
+    pub(crate) fn visit(&self, path: &Path, depth: usize) -> Result<Vec<DirEntry>, AnalyzerError> {
+        let (config, collector, link_handler) = (self.config, self.collector, self.link_handler);
+
+        // Stop when cancelled
+        if collector.is_cancelled() {
+            return Err(AnalyzerError::Cancelled);
+        }
+
+        // Check if path should be ignored
+        if config.should_ignore(path) {
+            return Ok(Vec::new());
+        }
+
+        // Check file count and size limits
+        if self.limit_reached() {
+            return Ok(Vec::new());
+        }
+
+        // Check if this is a circular symlink
+        let metadata = match self.walker.symlink_metadata(path) {
+            Ok(m) => m,
+            Err(e) => {
+                collector.add_warning_at(
+                    WarningKind::from_io_error(&e),
+                    Some(path),
+                    format!("Cannot access {}: {}", path.display(), e),
+                );
+                return Ok(Vec::new());
+            }
+        };
+
+        // Skip paths matched by .gitignore files
+        if let Some(gitignore) = &self.gitignore
+            && gitignore.is_ignored(path, metadata.is_dir())
+        {
+            return Ok(Vec::new());
+        }
+
+        if metadata.is_symlink()
+            && config.tracks_visited_paths()
+            && link_handler.is_circular(path).unwrap_or(false)
+        {
+            collector.add_warning_at(
+                WarningKind::SymlinkCycle,
+                Some(path),
+                format!("Circular symlink detected: {}", path.display()),
+            );
+            return Ok(Vec::new());
+        }
+
+        // Skip directories whose inode was already entered (cycle backstop)
+        if metadata.is_dir() && !link_handler.enter_directory(&metadata) {
+            collector.add_warning_at(
+                WarningKind::DirectoryCycle,
+                Some(path),
+                format!(
+                    "Directory cycle detected (inode already visited): {}",
+                    path.display()
+                ),
+            );
+            return Ok(Vec::new());
+        }
+
+        // Process file
+        if !metadata.is_dir() {
+            if (metadata.is_file() || metadata.is_symlink())
+                && let Some(entry) = self.processor.process_file(path, depth)?
+            {
+                collector.add_entry(entry);
+            }
+            return Ok(Vec::new());
+        }
+
+        // Mark the directory as visited
+        if config.tracks_visited_paths()
+            && let Err(e) = link_handler.mark_visited(path)
+        {
+            collector.add_warning_at(
+                WarningKind::from_error(&e),
+                Some(path),
+                format!("Failed to mark visited {}: {}", path.display(), e),
+            );
+        }
+        collector.add_directory(path, depth);
+        if config.record_dir_mtimes
+            && let Some(modified) = metadata.modified
+        {
+            collector.add_dir_mtime(path.to_path_buf(), modified);
+        }
+
+        // List the directory's children
+        let listing = match self
+            .walker
+            .read_dir(path, &metadata, depth, config.max_depth)
+        {
+            Ok(l) => l,
+            Err(e) => {
+                collector.add_warning_at(
+                    WarningKind::from_error(&e),
+                    Some(path),
+                    format!("Cannot read directory {}: {}", path.display(), e),
+                );
+                return Ok(Vec::new());
+            }
+        };
+        if listing.truncated {
+            collector.add_truncated_dir(path.to_path_buf());
+        }
+
+        // Record directories that live on a different device (mount points)
+        for entry in listing
+            .entries
+            .iter()
+            .filter(|e| e.crosses_device && !config.should_ignore(&e.path))
+        {
+            collector.add_mount_crossing(entry.path.clone());
+        }
+
+        Ok(listing.entries)
+    }
+}
 
 /// Trait for different directory traversal strategies
 pub trait TraversalStrategy: Send + Sync {
@@ -0,0 +1,107 @@
+// Parallel work-stealing traversal strategy
+//
+// Subdirectories are traversed concurrently on the rayon thread pool, so the
+// order of collected entries is nondeterministic. Aggregates (sizes and
+// counts) are the same as with the sequential strategies.
+
+use crate::collector::ResultCollector;
+use crate::config::AnalyzerConfig;
+use crate::error::AnalyzerError;
+use crate::link_handler::LinkHandler;
+use crate::traversal::{TraversalContext, TraversalStrategy};
+use crate::walker::DirectoryWalker;
+use rayon::prelude::*;
+use std::path::Path;
+use std::sync::Arc;
+
+pub struct ParallelTraversal;
+
+impl Default for ParallelTraversal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParallelTraversal {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn traverse_parallel(
+        &self,
+        path: &Path,
+        depth: usize,
+        ctx: &TraversalContext,
+    ) -> Result<(), AnalyzerError> {
+        // Traverse subdirectories concurrently
+        ctx.visit(path, depth)?.par_iter().try_for_each(|entry| {
+            if ctx.limit_reached() {
+                return Ok(());
+            }
+            self.traverse_parallel(&entry.path, entry.depth, ctx)
+        })
+    }
+}
+
+impl TraversalStrategy for ParallelTraversal {
+    fn traverse(
+        &self,
+        root: &Path,
+        config: &AnalyzerConfig,
+        walker: &DirectoryWalker,
+        link_handler: &Arc<LinkHandler>,
+        collector: &ResultCollector,
+    ) -> Result<(), AnalyzerError> {
+        let ctx = TraversalContext::new(root, config, walker, link_handler, collector);
+        self.traverse_parallel(root, 1, &ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::TraversalStrategy;
+    use crate::test_support;
+    use crate::{AnalysisResult, AnalyzerConfig, MemoryFileSystem};
+    use std::path::PathBuf;
+
+    fn scan(strategy: TraversalStrategy, fs: &MemoryFileSystem) -> AnalysisResult {
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.traversal_strategy = strategy;
+        test_support::scan(config, fs.clone())
+    }
+
+    #[test]
+    fn strategies_match_depth_first() {
+        let mut fs = MemoryFileSystem::new();
+        for dir in 0..8 {
+            for sub in 0..4 {
+                for file in 0..5 {
+                    fs.add_file(
+                        format!("/data/d{}/s{}/f{}", dir, sub, file),
+                        (dir * 100 + sub * 10 + file) as u64,
+                    );
+                }
+            }
+        }
+        fs.add_symlink("/data/d0/loop", "/data");
+
+        let sequential = scan(TraversalStrategy::DepthFirst, &fs);
+        let paths = |result: &AnalysisResult| {
+            let mut paths: Vec<PathBuf> = result.entries.iter().map(|e| e.path.clone()).collect();
+            paths.sort();
+            paths
+        };
+        // All strategies share the per-directory step, so they agree
+        for strategy in [TraversalStrategy::Parallel, TraversalStrategy::BreadthFirst] {
+            let other = scan(strategy, &fs);
+            assert_eq!(other.total_size, sequential.total_size);
+            assert_eq!(other.file_count, sequential.file_count);
+            assert_eq!(other.directory_count, sequential.directory_count);
+            assert_eq!(other.symlink_count, sequential.symlink_count);
+            assert_eq!(other.size_histogram, sequential.size_histogram);
+            assert_eq!(other.depth_histogram, sequential.depth_histogram);
+            assert_eq!(paths(&other), paths(&sequential));
+            assert_eq!(other.warnings.len(), sequential.warnings.len());
+        }
+    }
+}
@@ -1,8 +1,9 @@
 // Directory walking logic
 
 use crate::error::AnalyzerError;
+use crate::filesystem::{FileMetadata, FileSystem};
 use crate::link_handler::LinkHandler;
-use std::fs::{self, Metadata};
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -10,36 +11,73 @@ use std::sync::Arc;
 #[derive(Debug)]
 pub struct DirEntry {
     pub path: PathBuf,
-    pub metadata: Metadata,
+    pub metadata: FileMetadata,
     pub depth: usize,
+    /// Whether this entry is a directory on a different device than its parent
+    pub crosses_device: bool,
+}
+
+/// Entries read from one directory
+#[derive(Debug, Default)]
+pub struct DirListing {
+    pub entries: Vec<DirEntry>,
+    /// Whether entries beyond the per-directory limit were left out
+    pub truncated: bool,
 }
 
 /// Handles directory traversal with depth tracking
 pub struct DirectoryWalker {
     link_handler: Arc<LinkHandler>,
+    fs: Arc<dyn FileSystem>,
+    max_entries_per_dir: Option<usize>,
 }
 
 impl DirectoryWalker {
-    pub fn new(link_handler: Arc<LinkHandler>) -> Self {
-        Self { link_handler }
+    pub fn new(link_handler: Arc<LinkHandler>, fs: Arc<dyn FileSystem>) -> Self {
+        Self {
+            link_handler,
+            fs,
+            max_entries_per_dir: None,
+        }
+    }
+
+    /// Cap the number of entries read from any single directory
+    pub fn set_max_entries_per_dir(&mut self, max_entries: Option<usize>) {
+        self.max_entries_per_dir = max_entries;
+    }
+
+    /// Get the filesystem this walker reads from
+    pub fn file_system(&self) -> Arc<dyn FileSystem> {
+        self.fs.clone()
+    }
+
+    /// Get metadata for a path without following a final symlink
+    pub fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        self.fs.symlink_metadata(path)
     }
 
     /// Read directory entries at the given path
     pub fn read_dir(
         &self,
         path: &Path,
+        metadata: &FileMetadata,
         current_depth: usize,
         max_depth: Option<usize>,
-    ) -> Result<Vec<DirEntry>, AnalyzerError> {
+    ) -> Result<DirListing, AnalyzerError> {
         // Check if we should traverse this directory
         if !self.should_traverse_depth(current_depth, max_depth) {
-            return Ok(Vec::new());
+            return Ok(DirListing::default());
         }
 
         let mut entries = Vec::new();
+        let parent_device = metadata.dev;
 
-        // Read directory entries
-        let dir_entries = match fs::read_dir(path) {
+        // Read directory entries, one past the limit to detect truncation
+        let listed = match self.max_entries_per_dir {
+            Some(max) => self.fs.read_dir_limited(path, max.saturating_add(1)),
+            None => self.fs.read_dir(path),
+        };
+        let mut dir_entries = match listed {
             Ok(entries) => entries,
             Err(e) => {
                 // Return empty vec for permission denied or other errors
@@ -48,26 +86,26 @@ impl DirectoryWalker {
             }
         };
 
-        for entry in dir_entries {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue, // Skip entries we can't read
-            };
+        let truncated = self
+            .max_entries_per_dir
+            .is_some_and(|max| dir_entries.len() > max);
+        if let Some(max) = self.max_entries_per_dir {
+            dir_entries.truncate(max);
+        }
 
-            let path = entry.path();
-            let metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue, // Skip entries we can't get metadata for
-            };
+        for (path, metadata) in dir_entries {
+            let crosses_device =
+                metadata.is_dir() && parent_device.is_some() && metadata.dev != parent_device;
 
             entries.push(DirEntry {
                 path,
                 metadata,
                 depth: current_depth + 1,
+                crosses_device,
             });
         }
 
-        Ok(entries)
+        Ok(DirListing { entries, truncated })
     }
 
     /// Check if we should traverse to the next depth level
@@ -89,7 +127,7 @@ impl DirectoryWalker {
 
     /// Check if a path is a symbolic link and handle circular references
     pub fn check_symlink(&self, path: &Path) -> Result<bool, AnalyzerError> {
-        let metadata = fs::symlink_metadata(path)?;
+        let metadata = self.fs.symlink_metadata(path)?;
 
         if metadata.is_symlink() {
             // Check for circular reference
@@ -101,3 +139,67 @@ impl DirectoryWalker {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::filesystem::FileMetadata;
+    use crate::test_support::{FileSystemHooks, WrappedFileSystem, scan};
+    use crate::{AnalyzerConfig, MemoryFileSystem, WarningKind};
+    use std::path::{Path, PathBuf};
+
+    /// Reports everything under `/data/mnt` as living on a second device
+    struct Mounted;
+
+    impl FileSystemHooks for Mounted {
+        fn remap(&self, _inner: &MemoryFileSystem, path: &Path, metadata: &mut FileMetadata) {
+            if path.starts_with("/data/mnt") {
+                metadata.dev = Some(2);
+            }
+        }
+    }
+
+    #[test]
+    fn mount_crossings_are_recorded_once() {
+        let mut fs = MemoryFileSystem::new();
+        fs.add_file("/data/a.txt", 1)
+            .add_file("/data/mnt/b.txt", 2)
+            .add_file("/data/mnt/sub/c.txt", 3);
+
+        let result = scan(
+            AnalyzerConfig::new(PathBuf::from("/data")),
+            WrappedFileSystem {
+                inner: fs,
+                hooks: Mounted,
+            },
+        );
+
+        assert_eq!(result.mount_crossings(), [PathBuf::from("/data/mnt")]);
+        // Mounted directories are still scanned
+        assert_eq!(result.total_size, 6);
+    }
+
+    #[test]
+    fn per_directory_limit_truncates_listings() {
+        let mut fs = MemoryFileSystem::new();
+        for i in 0..5 {
+            fs.add_file(format!("/data/big/{}.txt", i), 1);
+        }
+        fs.add_file("/data/small/a.txt", 1);
+        let mut config = AnalyzerConfig::new(PathBuf::from("/data"));
+        config.max_entries_per_dir = Some(2);
+
+        let result = scan(config, fs);
+
+        assert_eq!(result.truncated_dirs, [PathBuf::from("/data/big")]);
+        assert_eq!(result.file_count, 3);
+        assert_eq!(
+            result.warnings,
+            ["Directory has too many entries, only the first were read: /data/big"]
+        );
+        assert_eq!(result.warning_details.len(), 1);
+        assert_eq!(
+            result.warning_details[0].kind,
+            WarningKind::TruncatedDirectory
+        );
+    }
+}
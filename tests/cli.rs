@@ -0,0 +1,310 @@
+// End-to-end tests running the built binary
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh directory under the system temp dir, removed on drop
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("wiztree-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run the binary with `args`, without any `WIZTREE_OPTS` defaults
+fn run<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wiztree-metafile"))
+        .env_remove("WIZTREE_OPTS")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run the binary listing files, with `root` as `WIZTREE_ROOT`
+fn list(root: &Path, args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wiztree-metafile"))
+        .env("WIZTREE_ROOT", root)
+        .env_remove("WIZTREE_OPTS")
+        .arg("--list")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn listed(output: &Output) -> Vec<PathBuf> {
+    assert!(output.status.success(), "{:?}", output);
+    let mut paths: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn root_env_is_scanned_without_a_path() {
+    let dir = TempDir::new("root-env");
+    std::fs::create_dir_all(dir.0.join("sub")).unwrap();
+    std::fs::write(dir.0.join("a.txt"), "hello").unwrap();
+    std::fs::write(dir.0.join("sub").join("b.txt"), "hi").unwrap();
+
+    assert_eq!(
+        listed(&list(&dir.0, &[])),
+        [dir.0.join("a.txt"), dir.0.join("sub").join("b.txt")]
+    );
+}
+
+#[test]
+fn path_argument_overrides_root_env() {
+    let env_root = TempDir::new("env-root");
+    let arg_root = TempDir::new("arg-root");
+    std::fs::write(env_root.0.join("ignored.txt"), "x").unwrap();
+    std::fs::write(arg_root.0.join("scanned.txt"), "y").unwrap();
+
+    assert_eq!(
+        listed(&list(&env_root.0, &[&arg_root.0])),
+        [arg_root.0.join("scanned.txt")]
+    );
+}
+
+#[test]
+fn output_template_writes_one_file_per_top_level_dir() {
+    let dir = TempDir::new("template");
+    let out = TempDir::new("template-out");
+    std::fs::create_dir_all(dir.0.join("docs")).unwrap();
+    std::fs::create_dir_all(dir.0.join("src").join("lib")).unwrap();
+    std::fs::create_dir_all(dir.0.join("assets")).unwrap();
+    std::fs::write(dir.0.join("docs").join("a.md"), "hello").unwrap();
+    std::fs::write(dir.0.join("src").join("b.rs"), "hi").unwrap();
+    std::fs::write(dir.0.join("src").join("lib").join("c.rs"), "1234").unwrap();
+    std::fs::write(dir.0.join("assets").join("d.png"), "x").unwrap();
+    // Files directly under the root belong to no group
+    std::fs::write(dir.0.join("top.txt"), "ignored").unwrap();
+
+    let template = out.0.join("{name}.json");
+    let output = run([
+        OsStr::new("--format"),
+        OsStr::new("json"),
+        OsStr::new("--output-template"),
+        template.as_os_str(),
+        dir.0.as_os_str(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut written: Vec<_> = std::fs::read_dir(&out.0)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["assets.json", "docs.json", "src.json"]);
+    for (name, total_size, file_count) in [("assets", 1, 1), ("docs", 5, 1), ("src", 6, 2)] {
+        let json = std::fs::read_to_string(out.0.join(format!("{}.json", name))).unwrap();
+        let result: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(result["total_size"], total_size, "{}", name);
+        assert_eq!(result["file_count"], file_count, "{}", name);
+    }
+}
+
+/// Peak memory reported by a `--peak-memory` scan of `root`, if tracked
+#[cfg(any(unix, windows))]
+fn peak_memory(root: &Path) -> Option<u64> {
+    let output = run([
+        OsStr::new("--format"),
+        OsStr::new("json"),
+        OsStr::new("--peak-memory"),
+        root.as_os_str(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    result["metadata"]["peak_memory"].as_u64()
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn peak_memory_grows_with_entry_count() {
+    let small = TempDir::new("peak-small");
+    let large = TempDir::new("peak-large");
+    for i in 0..10 {
+        std::fs::write(small.0.join(format!("file-{}.txt", i)), "").unwrap();
+    }
+    for i in 0..20_000 {
+        std::fs::write(large.0.join(format!("file-{}.txt", i)), "").unwrap();
+    }
+
+    // Each scan runs in its own process, so the peaks are not shared
+    let small_peak = peak_memory(&small.0).unwrap();
+    let large_peak = peak_memory(&large.0).unwrap();
+    assert!(large_peak > small_peak, "{} <= {}", large_peak, small_peak);
+}
+
+#[test]
+fn verbose_prints_a_summary_unless_quiet() {
+    let dir = TempDir::new("summary");
+    std::fs::write(dir.0.join("a.txt"), "hello").unwrap();
+    std::fs::write(dir.0.join("b.txt"), "hi").unwrap();
+    let stderr = |flags: &[&str]| {
+        let output = run(flags
+            .iter()
+            .map(OsStr::new)
+            .chain([OsStr::new("--list"), dir.0.as_os_str()]));
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let verbose = stderr(&["--verbose"]);
+    let summary = verbose.lines().next().unwrap();
+    let (counts, elapsed) = summary.rsplit_once(" in ").unwrap();
+    assert!(counts.starts_with("Scanned 2 files, "), "{}", summary);
+    let seconds = elapsed.strip_suffix('s').unwrap();
+    assert!(seconds.parse::<f64>().is_ok(), "{}", summary);
+    assert_eq!(seconds.split_once('.').unwrap().1.len(), 1, "{}", summary);
+
+    assert_eq!(stderr(&["--verbose", "--quiet"]), "");
+}
+
+#[test]
+fn reported_seed_reproduces_the_sample() {
+    let dir = TempDir::new("sample");
+    for i in 0..50 {
+        std::fs::write(dir.0.join(format!("file-{}.txt", i)), "x").unwrap();
+    }
+    // The seed is reported on stderr, the sampled paths listed on stdout
+    let sample = |seed: Option<&str>| {
+        let mut args = vec![
+            OsStr::new("--verbose"),
+            OsStr::new("--list"),
+            OsStr::new("--sample"),
+            OsStr::new("5"),
+            dir.0.as_os_str(),
+        ];
+        if let Some(seed) = seed {
+            args.extend([OsStr::new("--seed"), OsStr::new(seed)]);
+        }
+        let output = run(args);
+        assert!(output.status.success(), "{:?}", output);
+        let seed = String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find_map(|line| line.strip_prefix("Seed: "))
+            .unwrap()
+            .to_string();
+        (seed, listed(&output))
+    };
+
+    let (seed, first) = sample(None);
+    assert_eq!(first.len(), 5);
+    let (reported, again) = sample(Some(&seed));
+    assert_eq!(reported, seed);
+    assert_eq!(again, first);
+}
+
+#[test]
+fn kv_output_reports_the_seed_and_sampled_entries() {
+    let dir = TempDir::new("kv");
+    for i in 0..10 {
+        std::fs::write(dir.0.join(format!("file-{}.txt", i)), "x").unwrap();
+    }
+    let output = run([
+        OsStr::new("--format"),
+        OsStr::new("kv"),
+        OsStr::new("--kv-entries"),
+        OsStr::new("--sample"),
+        OsStr::new("3"),
+        OsStr::new("--seed"),
+        OsStr::new("42"),
+        dir.0.as_os_str(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Totals cover the whole scan, entries only the sample
+    assert!(
+        stdout.lines().any(|line| line == "file_count=10"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.lines().any(|line| line == "seed=42"), "{}", stdout);
+    let entries: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("size="))
+        .collect();
+    assert_eq!(entries.len(), 3, "{}", stdout);
+    assert!(entries.iter().all(|line| line.starts_with("size=1 path=")));
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn kv_output_reports_peak_memory() {
+    let dir = TempDir::new("kv-peak");
+    std::fs::write(dir.0.join("a.txt"), "x").unwrap();
+    let output = run([
+        OsStr::new("--format"),
+        OsStr::new("kv"),
+        OsStr::new("--peak-memory"),
+        dir.0.as_os_str(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let peak = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("peak_memory="))
+        .map(|peak| peak.parse::<u64>().unwrap());
+    assert!(peak.unwrap() > 0);
+}
+
+#[test]
+fn missing_reference_file_is_an_error() {
+    let dir = TempDir::new("reference");
+    std::fs::write(dir.0.join("a.txt"), "x").unwrap();
+    let missing = dir.0.join("missing-stamp");
+
+    let output = run([
+        OsStr::new("--list"),
+        OsStr::new("--newer-than"),
+        missing.as_os_str(),
+        dir.0.as_os_str(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("cannot read mtime of {}", missing.display())),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn reference_files_cannot_filter_a_manifest() {
+    let dir = TempDir::new("reference-manifest");
+    let manifest = dir.0.join("inventory.tsv");
+    let stamp = dir.0.join("stamp");
+    std::fs::write(&manifest, "/data/a.txt\t10\n").unwrap();
+    std::fs::write(&stamp, "").unwrap();
+
+    for flag in ["--newer-than", "--older-than"] {
+        let output = run([
+            OsStr::new("--manifest"),
+            manifest.as_os_str(),
+            OsStr::new(flag),
+            stamp.as_os_str(),
+        ]);
+        assert_eq!(output.status.code(), Some(1), "{}", flag);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("cannot be combined with --manifest"),
+            "{}",
+            stderr
+        );
+    }
+}